@@ -0,0 +1,622 @@
+//! Structural queries over a circuit.
+//! None of these evaluate the circuit. They only look at how gates and wires are connected.
+
+use crate::circuit::{Circuit, GateId, GateType, WireConnection, WireId};
+use crate::error::{CircuitError, CircuitResult};
+use std::collections::HashSet;
+
+/// Default cap on the number of paths `paths_between` enumerates.
+pub const DEFAULT_PATH_LIMIT: usize = 1024;
+
+/// Mark every wire which one of `roots` transitively depends on, including the roots themselves.
+pub(crate) fn backward_reachable(circuit: &Circuit, roots: &[WireId]) -> Vec<bool> {
+    let connections = circuit.wire_connections();
+    let gates = circuit.get_all_gates();
+    let mut visited = vec![false; circuit.get_wire_count()];
+
+    let mut stack: Vec<usize> = roots.iter().map(|w| w.0).collect();
+    while let Some(w) = stack.pop() {
+        if visited[w] {
+            continue;
+        }
+        visited[w] = true;
+
+        if let Some(gate_id) = connections[w].from_id {
+            stack.extend(gates[gate_id].get_inputs().iter().map(|w| w.0));
+        }
+    }
+    visited
+}
+
+/// Disjoint sets of wire ids, merged by union by size with path halving.
+pub(crate) struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(len: usize) -> Self {
+        UnionFind {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+        }
+    }
+
+    /// Returns the representative of the set containing `i`.
+    pub(crate) fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    /// Merge the sets containing `a` and `b`. Returns false if they were already the same set.
+    pub(crate) fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        true
+    }
+}
+
+impl Circuit {
+    /// Enumerate the paths from wire `from` to wire `to`.
+    /// Each path is the list of gate ids passed through, in order.
+    /// At most `DEFAULT_PATH_LIMIT` paths are returned. Use `paths_between_with_limit` to change the cap.
+    pub fn paths_between(&self, from: WireId, to: WireId) -> Vec<Vec<GateId>> {
+        self.paths_between_with_limit(from, to, DEFAULT_PATH_LIMIT)
+    }
+
+    /// Same as `paths_between`, but stop after `limit` paths have been found.
+    /// Number of paths can grow exponentially with the depth of dense circuits, so the limit caps the result.
+    /// The search only enters gates whose output `to` depends on, so on an acyclic circuit
+    /// every gate entered leads to a path, and the work is bounded by `limit` times the depth times the fan-out.
+    /// The search is iterative, so it works on circuits of any depth.
+    pub fn paths_between_with_limit(
+        &self,
+        from: WireId,
+        to: WireId,
+        limit: usize,
+    ) -> Vec<Vec<GateId>> {
+        let mut paths = vec![];
+        if limit == 0 || from.0 >= self.get_wire_count() || to.0 >= self.get_wire_count() {
+            return paths;
+        }
+        if from == to {
+            paths.push(vec![]);
+            return paths;
+        }
+
+        let leads_to_target = backward_reachable(self, &[to]);
+        // a gate reading a wire twice is one step, so it is entered once
+        let consumers: Vec<Vec<GateId>> = self
+            .wire_connections()
+            .iter()
+            .map(WireConnection::distinct_to_ids)
+            .collect();
+        let gates = self.get_all_gates();
+        let mut on_path = vec![false; gates.len()];
+        let mut current: Vec<GateId> = vec![];
+
+        // Frames of (wire id, index of the next gate reading it to try).
+        // Every frame but the first reads the output of the gate at the same depth of `current`.
+        let mut stack = vec![(from.0, 0)];
+        while let Some((wire_id, next)) = stack.last_mut() {
+            if paths.len() >= limit {
+                break;
+            }
+            let Some(&gate_id) = consumers[*wire_id].get(*next) else {
+                stack.pop();
+                if let Some(gate_id) = current.pop() {
+                    on_path[gate_id] = false;
+                }
+                continue;
+            };
+            *next += 1;
+
+            let out = gates[gate_id].get_output().0;
+            // a gate already on the current path means the circuit is cyclic.
+            if on_path[gate_id] || !leads_to_target[out] {
+                continue;
+            }
+
+            current.push(gate_id);
+            if out == to.0 {
+                paths.push(current.clone());
+                current.pop();
+            } else {
+                on_path[gate_id] = true;
+                stack.push((out, 0));
+            }
+        }
+
+        paths
+    }
+
+    /// Returns the input wires which the given output wire transitively depends on, in input-declaration order.
+    /// These are the only inputs a caller has to supply to compute `out`.
+    pub fn minimal_inputs_for(&self, out: WireId) -> CircuitResult<Vec<WireId>> {
+        if out.0 >= self.get_wire_count() {
+            return Err(CircuitError::UnknownWire { wire_id: out.0 });
+        }
+
+        let reachable = backward_reachable(self, &[out]);
+        Ok(self
+            .get_all_inputs()
+            .iter()
+            .filter(|input| reachable[input.0])
+            .copied()
+            .collect())
+    }
+
+    /// Returns the input wires on some path to `output`, as a set. See `minimal_inputs_for`.
+    /// The check is structural: an input stays in the set even if its value cancels out, as in `x + 0 * y`.
+    /// An unknown wire is influenced by no input.
+    pub fn influencing_inputs(&self, output: WireId) -> HashSet<WireId> {
+        self.minimal_inputs_for(output)
+            .map(|inputs| inputs.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Estimate how many gates would become constant if `input` were fixed to a constant.
+    /// A gate becomes constant when all of its input wires are constant,
+    /// so this counts the gates whose every input transitively derives from `input` and the constant wires,
+    /// and which don't derive from the constant wires alone.
+    pub fn fold_impact(&self, input: WireId) -> usize {
+        if input.0 >= self.get_wire_count() {
+            return 0;
+        }
+
+        let gates = self.get_all_gates();
+        let connections = self.wire_connections();
+
+        // number of reads of each gate from wires which are not known to be constant
+        let mut pending: Vec<usize> = gates.iter().map(|gate| gate.arity()).collect();
+        let mut is_constant = vec![false; self.get_wire_count()];
+
+        // mark `seeds` as constant, and return the number of gates which become constant
+        let mut fold = |seeds: Vec<usize>| {
+            let mut folded = 0;
+            let mut stack = vec![];
+            for wire in seeds {
+                if !is_constant[wire] {
+                    is_constant[wire] = true;
+                    stack.push(wire);
+                }
+            }
+            while let Some(wire) = stack.pop() {
+                for gate_id in connections[wire].to_ids.iter() {
+                    pending[*gate_id] -= 1;
+                    if pending[*gate_id] == 0 {
+                        folded += 1;
+                        let out = gates[*gate_id].get_output().0;
+                        if !is_constant[out] {
+                            is_constant[out] = true;
+                            stack.push(out);
+                        }
+                    }
+                }
+            }
+            folded
+        };
+
+        // gates depending only on constant wires are constant already, so they are not counted
+        fold(
+            self.get_constants()
+                .iter()
+                .map(|constant| constant.get_wire_id().0)
+                .collect(),
+        );
+        fold(vec![input.0])
+    }
+
+    /// Returns the number of gates on the longest path made only of consecutive Mul gates.
+    /// Returns an error if the gates can't be ordered, see `gate_layers`.
+    pub fn longest_mul_chain(&self) -> CircuitResult<usize> {
+        let gates = self.get_all_gates();
+        let connections = self.wire_connections();
+
+        // length of the longest mul chain ending at each gate
+        let mut chain_length = vec![0; gates.len()];
+        for id in self.gate_layers()?.into_iter().flatten() {
+            let gate = &gates[id];
+            if gate.gate_type() != GateType::Mul {
+                continue;
+            }
+            let longest_predecessor = gate
+                .get_inputs()
+                .into_iter()
+                .filter_map(|w| connections[w.0].from_id)
+                .map(|p| chain_length[p])
+                .max()
+                .unwrap_or(0);
+            chain_length[id] = longest_predecessor + 1;
+        }
+
+        Ok(chain_length.into_iter().max().unwrap_or(0))
+    }
+
+    /// Returns ids of the gates which dominate some output wire, sorted by id.
+    /// A gate dominates an output if every path from an input wire to the output passes through it.
+    /// The gate producing an output always dominates it.
+    ///
+    /// Immediate dominators are computed over the gates in topological order,
+    /// with a virtual root in front of all the input wires.
+    /// Returns an error if the gates can't be ordered, see `gate_layers`.
+    pub fn dominator_gates(&self) -> CircuitResult<Vec<GateId>> {
+        let gates = self.get_all_gates();
+        let connections = self.wire_connections();
+        let order: Vec<GateId> = self.gate_layers()?.into_iter().flatten().collect();
+
+        let mut position = vec![0; gates.len()];
+        for (i, id) in order.iter().enumerate() {
+            position[*id] = i;
+        }
+
+        // immediate dominator of each gate. None is the virtual root.
+        let mut idom: Vec<Option<GateId>> = vec![None; gates.len()];
+        let intersect = |idom: &[Option<GateId>], mut a: Option<GateId>, mut b: Option<GateId>| {
+            while let (Some(ga), Some(gb)) = (a, b) {
+                if ga == gb {
+                    break;
+                }
+                if position[ga] > position[gb] {
+                    a = idom[ga];
+                } else {
+                    b = idom[gb];
+                }
+            }
+            a.and(b)
+        };
+
+        for id in order.iter() {
+            let predecessors: Option<Vec<GateId>> = gates[*id]
+                .get_inputs()
+                .iter()
+                .map(|w| connections[w.0].from_id)
+                .collect();
+            // an input wire is reached directly from the root
+            idom[*id] = predecessors.and_then(|predecessors| {
+                predecessors
+                    .into_iter()
+                    .map(Some)
+                    .reduce(|a, b| intersect(&idom, a, b))
+                    .flatten()
+            });
+        }
+
+        let mut is_dominator = vec![false; gates.len()];
+        for out in self.get_all_outputs() {
+            let mut dominator = connections[out.0].from_id;
+            while let Some(id) = dominator {
+                if is_dominator[id] {
+                    break;
+                }
+                is_dominator[id] = true;
+                dominator = idom[id];
+            }
+        }
+
+        Ok((0..gates.len()).filter(|id| is_dominator[*id]).collect())
+    }
+
+    /// Returns true if the circuit has no shared subexpressions,
+    /// i.e. no wire other than output wires is read by more than one gate.
+    pub fn is_tree(&self) -> bool {
+        let mut is_output = vec![false; self.get_wire_count()];
+        for out in self.get_all_outputs() {
+            is_output[out.0] = true;
+        }
+
+        self.wire_connections()
+            .iter()
+            .enumerate()
+            .all(|(wire_id, connection)| {
+                is_output[wire_id] || connection.distinct_to_ids().len() <= 1
+            })
+    }
+
+    /// Returns the number of connected components of the circuit,
+    /// where the wires of a gate are connected to each other regardless of direction.
+    /// A wire which is not read or written by any gate is a component on its own.
+    pub fn component_count(&self) -> usize {
+        let wire_offset = self.get_wire_offset();
+        let mut components = UnionFind::new(self.get_wire_count());
+        let mut count = self.get_wire_count() - wire_offset;
+
+        for gate in self.gate_stream() {
+            let out = gate.get_output();
+            for input in gate.get_inputs() {
+                if components.union(input.0, out.0) {
+                    count -= 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Total number of wire reads by all the gates, i.e. the sum of gate arities.
+    pub fn total_fan_in(&self) -> usize {
+        self.gate_stream().map(|gate| gate.arity()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::test_utils::Fp;
+    use std::collections::HashSet;
+
+    // Circuit
+    // a = in1 + in2 (gate0)
+    // b = in1 * in2 (gate1)
+    // out = a + b   (gate2)
+    fn diamond() -> (Circuit, WireId, WireId, WireId) {
+        let mut circuit = Circuit::new();
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        let out = circuit.create_new_wire();
+
+        circuit.add_gate(GateType::Add, in1, in2, a);
+        circuit.add_gate(GateType::Mul, in1, in2, b);
+        circuit.add_gate(GateType::Add, a, b, out);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_output(out).unwrap();
+
+        (circuit, in1, in2, out)
+    }
+
+    #[test]
+    fn paths_in_diamond_circuit() {
+        let (circuit, in1, _, out) = diamond();
+        let paths = circuit.paths_between(in1, out);
+        assert_eq!(
+            paths,
+            vec![vec![0, 2], vec![1, 2]],
+            "in1 reaches out through gate0 and through gate1"
+        );
+    }
+
+    #[test]
+    fn minimal_inputs_of_outputs() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        assert_eq!(circuit.minimal_inputs_for(out1), Ok(vec![in1, in2]));
+        assert_eq!(circuit.minimal_inputs_for(out2), Ok(vec![in1, in2, in3]));
+        assert_eq!(
+            circuit.minimal_inputs_for(WireId(10)),
+            Err(CircuitError::UnknownWire { wire_id: 10 })
+        );
+    }
+
+    #[test]
+    fn influencing_inputs_exclude_disconnected_input() {
+        // out1 = x + 0 * y
+        // out2 = x * z
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let z = circuit.add_input();
+        let zero = circuit.create_constant_wire(Fp::from(0));
+        let ignored = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, zero, y, ignored);
+        let out1 = circuit.add_output_gate(GateType::Add, x, ignored);
+        let out2 = circuit.add_output_gate(GateType::Mul, x, z);
+
+        assert_eq!(circuit.influencing_inputs(out2), HashSet::from([x, z]));
+        // y is multiplied by 0, but still connected
+        assert_eq!(circuit.influencing_inputs(out1), HashSet::from([x, y]));
+        assert!(circuit.influencing_inputs(WireId(10)).is_empty());
+    }
+
+    #[test]
+    fn fold_impact_of_inputs() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // a = in1 * in1
+        // b = a + in1
+        // out1 = b * in2
+        // out2 = in2 + in2
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in1, in1, a);
+        circuit.add_gate(GateType::Add, a, in1, b);
+        circuit.add_gate(GateType::Mul, b, in2, out1);
+        circuit.add_gate(GateType::Add, in2, in2, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        assert_eq!(circuit.fold_impact(in1), 2, "Fixing in1 folds a and b");
+        assert_eq!(circuit.fold_impact(in2), 1, "Fixing in2 folds only out2");
+    }
+
+    #[test]
+    fn fold_impact_with_constants() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // k = 2 * 3
+        // out = x * k
+        let x = circuit.add_input();
+        let two = circuit.create_constant_wire(Fp::from(2));
+        let three = circuit.create_constant_wire(Fp::from(3));
+        let k = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, two, three, k);
+        let out = circuit.create_new_wire();
+        circuit.add_gate(GateType::ScalarMul, x, k, out);
+        circuit.mark_output(out).unwrap();
+
+        assert_eq!(circuit.fold_impact(x), 1, "Fixing x folds only out");
+    }
+
+    #[test]
+    fn longest_mul_chain_stops_at_add() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // a = in * in, b = a * in, c = b * in
+        // d = c + in
+        // out = d * in
+        let input = circuit.add_input();
+        let mut acc = input;
+        for gate_type in [GateType::Mul, GateType::Mul, GateType::Mul, GateType::Add] {
+            let next = circuit.create_new_wire();
+            circuit.add_gate(gate_type, acc, input, next);
+            acc = next;
+        }
+        circuit.add_output_gate(GateType::Mul, acc, input);
+
+        assert_eq!(circuit.longest_mul_chain(), Ok(3));
+    }
+
+    #[test]
+    fn bottleneck_gate_dominates_output() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // s = in1 + in2 (gate0)
+        // u = s * s     (gate1)
+        // v = s + s     (gate2)
+        // out = u * v   (gate3)
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let s = circuit.create_new_wire();
+        let u = circuit.create_new_wire();
+        let v = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, s);
+        circuit.add_gate(GateType::Mul, s, s, u);
+        circuit.add_gate(GateType::Add, s, s, v);
+        circuit.add_output_gate(GateType::Mul, u, v);
+
+        assert_eq!(circuit.dominator_gates(), Ok(vec![0, 3]));
+
+        // in the diamond, every input reaches the output through two gates
+        let (circuit, _, _, _) = diamond();
+        assert_eq!(circuit.dominator_gates(), Ok(vec![2]));
+    }
+
+    #[test]
+    fn tree_and_shared_subexpression() {
+        let mut circuit = Circuit::new();
+
+        // out = (in1 + in2) * (in3 + in4)
+        let inputs: Vec<WireId> = (0..4).map(|_| circuit.add_input()).collect();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, inputs[0], inputs[1], a);
+        circuit.add_gate(GateType::Add, inputs[2], inputs[3], b);
+        circuit.add_output_gate(GateType::Mul, a, b);
+        assert!(circuit.is_tree(), "Every wire is read once");
+
+        // in1 and in2 of the diamond are both read by two gates
+        let (circuit, _, _, _) = diamond();
+        assert!(!circuit.is_tree(), "Inputs are shared by two gates");
+    }
+
+    #[test]
+    fn component_count_of_disjoint_adders() {
+        let mut circuit = Circuit::new();
+        for _ in 0..2 {
+            let x = circuit.add_input();
+            let y = circuit.add_input();
+            circuit.add_output_gate(GateType::Add, x, y);
+        }
+        assert_eq!(circuit.component_count(), 2);
+
+        let (circuit, _, _, _) = diamond();
+        assert_eq!(circuit.component_count(), 1);
+    }
+
+    #[test]
+    fn total_fan_in_counts_every_read() {
+        let (mut circuit, in1, _, _) = diamond();
+        assert_eq!(circuit.total_fan_in(), 6);
+
+        // reading the same wire twice is two reads
+        let square = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in1, in1, square);
+        assert_eq!(circuit.total_fan_in(), 8);
+        assert_eq!(Circuit::new().total_fan_in(), 0);
+    }
+
+    #[test]
+    fn paths_enumeration_respects_limit() {
+        let (circuit, _, in2, out) = diamond();
+        let paths = circuit.paths_between_with_limit(in2, out, 1);
+        assert_eq!(paths.len(), 1, "Only one path should be returned");
+    }
+
+    #[test]
+    fn paths_in_deep_circuit() {
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input();
+        let mut acc = input;
+        for _ in 0..200_000 {
+            acc = circuit.add_neg(acc);
+        }
+        circuit.mark_output(acc).unwrap();
+
+        let paths = circuit.paths_between(input, acc);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), 200_000);
+    }
+
+    #[test]
+    fn paths_to_unreachable_wire() {
+        // ladder of 40 levels with 2 gates each, so there are 2^40 paths through it
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input();
+        let (mut a, mut b) = (input, input);
+        for _ in 0..40 {
+            let (next_a, next_b) = (circuit.create_new_wire(), circuit.create_new_wire());
+            circuit.add_gate(GateType::Add, a, b, next_a);
+            circuit.add_gate(GateType::Mul, a, b, next_b);
+            (a, b) = (next_a, next_b);
+        }
+        let unreachable = circuit.create_new_wire();
+
+        assert!(circuit
+            .paths_between_with_limit(input, unreachable, 1)
+            .is_empty());
+        assert_eq!(circuit.paths_between_with_limit(input, a, 3).len(), 3);
+    }
+}
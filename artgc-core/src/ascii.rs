@@ -0,0 +1,157 @@
+//! ASCII rendering of small circuits, for printing them in terminals and test failure messages.
+//!
+//! Gates are drawn as boxes, one row of boxes per layer (see `Circuit::layers`),
+//! with the outputs at the top and the inputs at the bottom like the diagrams in the tests of `detect_cycle`.
+//! Each box shows its output wire above it and its input wires below it.
+//! Edges are not drawn, so wires are matched by their ids. This is a best effort for readability, not a layout engine.
+
+use crate::circuit::{Circuit, Gate, GateType, WireId};
+use crate::error::{CircuitError, CircuitResult};
+
+/// Largest number of gates `Circuit::to_ascii` renders. Larger circuits don't fit in a terminal anyway.
+pub const ASCII_GATE_LIMIT: usize = 64;
+
+fn symbol(gate: &Gate) -> &'static str {
+    match gate.gate_type() {
+        GateType::Add => "+",
+        GateType::Sub => "-",
+        GateType::Mul => "*",
+        GateType::Neg => "neg",
+        GateType::ScalarMul => "*k",
+    }
+}
+
+fn wire_list<'a>(wires: impl IntoIterator<Item = &'a WireId>) -> String {
+    wires
+        .into_iter()
+        .map(|wire| format!("w{}", wire.0))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn center(text: &str, width: usize) -> String {
+    let padding = width - text.chars().count();
+    let left = padding / 2;
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(padding - left))
+}
+
+impl Circuit {
+    /// Render the circuit as a top-down ASCII diagram.
+    /// Gates which can't be evaluated, e.g. because they are on a cycle, have no layer and are not drawn.
+    ///
+    /// Returns `CircuitError::CircuitTooLarge` if the circuit has more than `ASCII_GATE_LIMIT` gates.
+    pub fn to_ascii(&self) -> CircuitResult<String> {
+        if self.get_gate_count() > ASCII_GATE_LIMIT {
+            return Err(CircuitError::CircuitTooLarge {
+                gate_count: self.get_gate_count(),
+                limit: ASCII_GATE_LIMIT,
+            });
+        }
+
+        let gates = self.get_all_gates();
+        let mut lines = vec![format!("outputs: {}", wire_list(self.get_all_outputs()))];
+        for layer in self.layers().gate_layers.iter().rev() {
+            // a gate is drawn as 5 lines: output wire, top of the box, symbol, bottom of the box, input wires
+            let mut rows = vec![String::new(); 5];
+            for gate_id in layer {
+                let gate = &gates[*gate_id];
+                let symbol = symbol(gate);
+                let bar = "─".repeat(symbol.chars().count() + 2);
+                let cell = [
+                    format!("w{}", gate.get_output().0),
+                    format!("┌{}┐", bar),
+                    format!("│ {} │", symbol),
+                    format!("└{}┘", bar),
+                    wire_list(&gate.get_inputs()),
+                ];
+                let width = cell.iter().map(|line| line.chars().count()).max().unwrap();
+                for (row, line) in rows.iter_mut().zip(cell) {
+                    if !row.is_empty() {
+                        row.push_str("  ");
+                    }
+                    row.push_str(&center(&line, width));
+                }
+            }
+            lines.extend(rows.into_iter().map(|row| row.trim_end().to_string()));
+        }
+
+        let constants: Vec<WireId> = self
+            .get_constants()
+            .iter()
+            .map(|constant| constant.get_wire_id())
+            .collect();
+        if !constants.is_empty() {
+            lines.push(format!("constants: {}", wire_list(&constants)));
+        }
+        lines.push(format!("inputs: {}", wire_list(self.get_all_inputs())));
+
+        Ok(lines.join("\n") + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ASCII_GATE_LIMIT;
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+
+    #[test]
+    fn render_add_gate() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, x, y);
+
+        let ascii = circuit.to_ascii().unwrap();
+        assert!(ascii.contains("┌───┐\n│ + │\n└───┘"));
+        assert!(ascii.starts_with("outputs: w2\n"));
+        assert!(ascii.ends_with("inputs: w0 w1\n"));
+        assert_eq!(
+            ascii,
+            "outputs: w2\n w2\n┌───┐\n│ + │\n└───┘\nw0 w1\ninputs: w0 w1\n"
+        );
+    }
+
+    #[test]
+    fn render_layers_top_down() {
+        let mut circuit = Circuit::new();
+
+        // out = (x + y) * neg(x)
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        let neg = circuit.add_neg(x);
+        circuit.add_output_gate(GateType::Mul, sum, neg);
+
+        let ascii = circuit.to_ascii().unwrap();
+        let mul = ascii.find("│ * │").unwrap();
+        let add = ascii.find("│ + │").unwrap();
+        let neg = ascii.find("│ neg │").unwrap();
+        assert!(mul < add && add < neg, "{}", ascii);
+        // both gates of the first layer are on the same row
+        assert_eq!(
+            ascii[..add].matches('\n').count(),
+            ascii[..neg].matches('\n').count()
+        );
+    }
+
+    #[test]
+    fn large_circuit_is_rejected() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let mut acc = x;
+        for _ in 0..=ASCII_GATE_LIMIT {
+            acc = circuit.add_neg(acc);
+        }
+        circuit.mark_output(acc).unwrap();
+
+        assert_eq!(
+            circuit.to_ascii(),
+            Err(CircuitError::CircuitTooLarge {
+                gate_count: ASCII_GATE_LIMIT + 1,
+                limit: ASCII_GATE_LIMIT
+            })
+        );
+    }
+}
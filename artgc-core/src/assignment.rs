@@ -0,0 +1,118 @@
+//! Full wire assignments of a circuit.
+//! An assignment holds a value for every wire, in wire id order.
+//! The value of wire `w` is at index `w - wire_offset`.
+//! It is satisfying if the output of every gate equals the gate applied to its inputs,
+//! which is the arithmetic circuit satisfiability relation used by proof systems.
+
+use crate::circuit::Circuit;
+use crate::error::{CircuitError, CircuitResult};
+use crate::eval_local::evaluate_wires;
+use crate::ring::Ring;
+
+impl Circuit {
+    /// Check that every gate is locally consistent with the values in `assignment`,
+    /// and that constant wires are assigned their values.
+    /// Unlike `eval_local`, values are not propagated: each gate is recomputed from the asserted input values
+    /// and compared with the asserted output value.
+    ///
+    /// Returns an error if `assignment` doesn't have exactly one value per wire.
+    pub fn check_assignment<T: Ring>(&self, assignment: &[T]) -> CircuitResult<bool> {
+        let wire_offset = self.get_wire_offset();
+        if assignment.len() != self.get_wire_count() - wire_offset {
+            return Err(CircuitError::AssignmentLengthMismatch {
+                expected: self.get_wire_count() - wire_offset,
+                got: assignment.len(),
+            });
+        }
+
+        let constants_hold = self.get_constants().iter().all(|constant| {
+            constant.get_value::<T>() == Some(&assignment[constant.get_wire_id().0 - wire_offset])
+        });
+
+        Ok(constants_hold
+            && self.get_all_gates().iter().all(|gate| {
+                let inputs = gate.get_inputs();
+                let values = inputs.iter().map(|w| assignment[w.0 - wire_offset].clone());
+                gate.evaluate(values) == assignment[gate.get_output().0 - wire_offset]
+            }))
+    }
+
+    /// Evaluate the circuit and return the value of every wire, in wire id order.
+    /// The result is a satisfying assignment which can be handed to a prover as a witness.
+    ///
+    /// Returns an error if the number of inputs doesn't match,
+    /// or with the first wire which can't be evaluated from the inputs.
+    pub fn witness<T: Ring>(&self, inputs: Vec<T>) -> CircuitResult<Vec<T>> {
+        if inputs.len() != self.get_all_inputs().len() {
+            return Err(CircuitError::InputLengthMismatch {
+                expected: self.get_all_inputs().len(),
+                got: inputs.len(),
+            });
+        }
+
+        let (values, _) = evaluate_wires(self, inputs, true);
+        values
+            .into_iter()
+            .enumerate()
+            .skip(self.get_wire_offset())
+            .map(|(wire_id, value)| value.ok_or(CircuitError::UndrivenWire { wire_id }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn check_correct_and_corrupted_assignment() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let in3 = circuit.add_input();
+        let out1 = circuit.add_output_gate(GateType::Add, in1, in2);
+        circuit.add_output_gate(GateType::Mul, out1, in3);
+
+        let mut assignment: Vec<Fp> = [1, 2, 3, 3, 9].map(Fp::from).to_vec();
+        assert_eq!(circuit.check_assignment(&assignment), Ok(true));
+
+        assignment[out1.0] = Fp::from(4);
+        assert_eq!(circuit.check_assignment(&assignment), Ok(false));
+
+        assert_eq!(
+            circuit.check_assignment(&assignment[..4]),
+            Err(CircuitError::AssignmentLengthMismatch {
+                expected: 5,
+                got: 4
+            })
+        );
+    }
+
+    #[test]
+    fn witness_contains_every_wire() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 * in2
+        // out2 = (in1 * in2) + in1
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let out1 = circuit.add_output_gate(GateType::Mul, in1, in2);
+        let out2 = circuit.add_output_gate(GateType::Add, out1, in1);
+
+        let inputs: Vec<Fp> = vec![3.into(), 5.into()];
+        let witness = circuit.witness(inputs.clone()).unwrap();
+        assert_eq!(witness.len(), circuit.get_wire_count());
+
+        let outputs = eval_local(&circuit, inputs).unwrap();
+        assert_eq!(outputs, vec![witness[out1.0], witness[out2.0]]);
+        assert_eq!(circuit.check_assignment(&witness), Ok(true));
+    }
+}
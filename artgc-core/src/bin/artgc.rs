@@ -0,0 +1,123 @@
+//! Evaluate a circuit from the command line.
+//!
+//! ```text
+//! artgc --circuit circuit.json --inputs inputs.json [--modulus m61]
+//! ```
+//!
+//! The circuit is the JSON serialization of `Circuit`, and the inputs are a JSON array of integers, in input order.
+//! Constant wires can't be serialized, so circuits with constants or `ScalarMul` gates can't be evaluated this way.
+//! The circuit is evaluated over the prime field of `--modulus`, and its outputs are printed one per line, in output order.
+//!
+//! Exits with 1 if a file can't be read or parsed, 3 if the circuit is invalid, and 4 if evaluation fails.
+//! clap exits with 2 on bad arguments.
+
+use artgc_core::circuit::Circuit;
+use artgc_core::dyn_ring::{eval_local_dyn, DynRing, Modulus};
+use artgc_core::error::CircuitError;
+use clap::{Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "artgc", about = "Evaluate an arithmetic circuit")]
+struct Args {
+    /// JSON file of the circuit
+    #[arg(long)]
+    circuit: PathBuf,
+    /// JSON file of the input values, an array of integers in input order
+    #[arg(long)]
+    inputs: PathBuf,
+    /// Field to evaluate the circuit over
+    #[arg(long, value_enum, default_value_t = Field::M61)]
+    modulus: Field,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Field {
+    /// 2^31 - 1
+    M31,
+    /// 2^61 - 1
+    M61,
+    /// 2^64 - 2^32 + 1
+    Goldilocks,
+}
+
+impl From<Field> for Modulus {
+    fn from(field: Field) -> Self {
+        match field {
+            Field::M31 => Modulus::Mersenne31,
+            Field::M61 => Modulus::Mersenne61,
+            Field::Goldilocks => Modulus::Goldilocks,
+        }
+    }
+}
+
+/// Failure of a run, with the exit code reporting it.
+enum Failure {
+    Read(String),
+    Invalid(CircuitError),
+    Evaluation(CircuitError),
+}
+
+impl Failure {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Read(_) => 1,
+            Failure::Invalid(_) => 3,
+            Failure::Evaluation(_) => 4,
+        }
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, Failure> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Failure::Read(format!("can't read {}: {}", path.display(), e)))?;
+    serde_json::from_str(&text)
+        .map_err(|e| Failure::Read(format!("can't parse {}: {}", path.display(), e)))
+}
+
+fn run(args: Args) -> Result<Vec<u64>, Failure> {
+    let circuit: Circuit = read_json(&args.circuit)?;
+    let values: Vec<u64> = read_json(&args.inputs)?;
+
+    circuit.is_valid().map_err(Failure::Invalid)?;
+
+    let modulus = Modulus::from(args.modulus);
+    let inputs: Vec<DynRing> = values
+        .into_iter()
+        .map(|value| DynRing::new(value, modulus))
+        .collect();
+    circuit.check_inputs(&inputs).map_err(Failure::Evaluation)?;
+    let outputs =
+        eval_local_dyn(&circuit, inputs).map_err(|e| Failure::Evaluation(CircuitError::from(e)))?;
+
+    Ok(outputs
+        .into_iter()
+        .map(|output| {
+            // an output computed from constants only may still be an integer
+            match output + DynRing::new(0, modulus) {
+                DynRing::Field { value, .. } => value,
+                _ => unreachable!("eval_local_dyn checks that all the values are in one field"),
+            }
+        })
+        .collect())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(outputs) => {
+            for output in outputs {
+                println!("{}", output);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(failure) => {
+            match &failure {
+                Failure::Read(message) => eprintln!("error: {}", message),
+                Failure::Invalid(error) => eprintln!("error: invalid circuit: {}", error),
+                Failure::Evaluation(error) => eprintln!("error: {}", error),
+            }
+            ExitCode::from(failure.exit_code())
+        }
+    }
+}
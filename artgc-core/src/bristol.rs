@@ -0,0 +1,307 @@
+//! Import and export of circuits in Bristol Fashion, the format most MPC circuit collections are published in.
+//!
+//! Bristol circuits are boolean, so their gates are mapped onto gates computing the same function over GF(2):
+//! `XOR` becomes `Add` and `AND` becomes `Mul`. On export, `Sub` is written as `XOR` as well.
+//! The circuit only computes the original function when it is evaluated over GF(2).
+//! `INV`, `EQ`, `EQW` and `MAND` have no counterpart here and are rejected.
+
+use crate::circuit::{Circuit, GateType, WireId};
+use crate::error::{CircuitError, CircuitResult};
+use crate::topo::topological_sort;
+use std::io::{self, BufRead, Write};
+
+fn parse_error(line: usize, message: impl Into<String>) -> CircuitError {
+    CircuitError::BristolParse {
+        line,
+        message: message.into(),
+    }
+}
+
+fn parse_numbers(line: usize, tokens: &[&str]) -> CircuitResult<Vec<usize>> {
+    tokens
+        .iter()
+        .map(|token| {
+            token
+                .parse()
+                .map_err(|_| parse_error(line, format!("`{}` is not a number", token)))
+        })
+        .collect()
+}
+
+/// Parse a line of a count followed by that many numbers, like the input and output lines of the header.
+fn parse_groups(line: usize, text: &str) -> CircuitResult<Vec<usize>> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let numbers = parse_numbers(line, &tokens)?;
+    match numbers.split_first() {
+        Some((count, groups)) if *count == groups.len() => Ok(groups.to_vec()),
+        _ => Err(parse_error(
+            line,
+            "expected the number of groups followed by the size of each group",
+        )),
+    }
+}
+
+impl Circuit {
+    /// Read a circuit in Bristol Fashion.
+    /// Bristol wire `i` becomes `WireId(i)`. Input wires are marked in order, group after group,
+    /// and the last wires are marked as outputs, as the format defines.
+    /// The circuit is checked with `is_valid` before it is returned.
+    ///
+    /// Returns `CircuitError::BristolParse` with the line number if the text is malformed
+    /// or uses a gate other than `XOR` and `AND`.
+    pub fn from_bristol(reader: impl BufRead) -> CircuitResult<Circuit> {
+        let mut lines = vec![];
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| parse_error(i + 1, e.to_string()))?;
+            if !line.trim().is_empty() {
+                lines.push((i + 1, line));
+            }
+        }
+        let end = lines.last().map_or(1, |(number, _)| number + 1);
+        let mut lines = lines.into_iter();
+        let mut next_line = |what: &str| {
+            lines
+                .next()
+                .ok_or_else(|| parse_error(end, format!("expected {}", what)))
+        };
+
+        let (number, header) = next_line("the gate and wire counts")?;
+        let tokens: Vec<&str> = header.split_whitespace().collect();
+        let (gate_count, wire_count) = match parse_numbers(number, &tokens)?[..] {
+            [gate_count, wire_count] => (gate_count, wire_count),
+            _ => return Err(parse_error(number, "expected the gate and wire counts")),
+        };
+
+        let (number, inputs) = next_line("the input groups")?;
+        let input_count: usize = parse_groups(number, &inputs)?.iter().sum();
+        let (number, outputs) = next_line("the output groups")?;
+        let output_count: usize = parse_groups(number, &outputs)?.iter().sum();
+        if input_count + output_count > wire_count {
+            return Err(parse_error(
+                number,
+                "there are more input and output wires than wires",
+            ));
+        }
+
+        let mut circuit = Circuit::new();
+        for _ in 0..wire_count {
+            circuit.create_new_wire();
+        }
+        for i in 0..input_count {
+            circuit.mark_input(WireId(i))?;
+        }
+        for i in wire_count - output_count..wire_count {
+            circuit.mark_output(WireId(i))?;
+        }
+
+        for _ in 0..gate_count {
+            let (number, line) = next_line("a gate")?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            // blank lines are skipped, so there is at least one token
+            let (mnemonic, wires) = tokens.split_last().unwrap();
+            let gate_type = match *mnemonic {
+                "XOR" => GateType::Add,
+                "AND" => GateType::Mul,
+                other => {
+                    return Err(parse_error(
+                        number,
+                        format!("unsupported gate `{}`, only XOR and AND are", other),
+                    ))
+                }
+            };
+
+            let wires = parse_numbers(number, wires)?;
+            let (x, y, out) = match wires[..] {
+                [2, 1, x, y, out] => (x, y, out),
+                _ => {
+                    return Err(parse_error(
+                        number,
+                        format!("{} gate must have 2 inputs and 1 output", mnemonic),
+                    ))
+                }
+            };
+            if let Some(wire) = [x, y, out].into_iter().find(|w| *w >= wire_count) {
+                return Err(parse_error(
+                    number,
+                    format!("wire {} is out of range", wire),
+                ));
+            }
+            circuit.add_gate(gate_type, WireId(x), WireId(y), WireId(out));
+        }
+
+        if let Some((number, _)) = lines.next() {
+            return Err(parse_error(
+                number,
+                "there are more gates than the gate count",
+            ));
+        }
+
+        circuit.is_valid()?;
+        Ok(circuit)
+    }
+
+    /// Write the circuit in Bristol Fashion, with gates in topological order.
+    /// Bristol requires input wires to come first and output wires to come last,
+    /// so wires are renumbered: inputs and outputs in declaration order, the other wires in id order.
+    /// Inputs and outputs are each written as a single group.
+    /// Wires which are not an input, an output or a part of any gate are dropped.
+    ///
+    /// Fails with `io::ErrorKind::InvalidInput` if the circuit has constant wires, `AddMany` gates or a cycle,
+    /// or if a wire is declared as an input or output more than once, since it can't take two positions.
+    pub fn to_bristol(&self, mut writer: impl Write) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if !self.get_constants().is_empty() {
+            return Err(invalid(
+                "constant wires can't be written in Bristol Fashion".into(),
+            ));
+        }
+        if self.gate_stream().any(|gate| gate.arity() != 2) {
+            return Err(invalid(
+                "only gates with 2 inputs can be written in Bristol Fashion".into(),
+            ));
+        }
+        let order = topological_sort(self).map_err(|e| invalid(e.to_string()))?;
+
+        let inputs = self.get_all_inputs();
+        let outputs = self.get_all_outputs();
+        let gates = self.get_all_gates();
+
+        let mut used = vec![false; self.get_wire_count()];
+        for gate in gates.iter() {
+            for wire in gate.get_inputs() {
+                used[wire.0] = true;
+            }
+            used[gate.get_output().0] = true;
+        }
+
+        // new id of each wire
+        let mut ids: Vec<Option<usize>> = vec![None; self.get_wire_count()];
+        let mut next_id = 0;
+        let mut assign = |ids: &mut Vec<Option<usize>>, wire: WireId| {
+            if ids[wire.0].is_some() {
+                return Err(invalid(format!(
+                    "wire with id{} is declared as an input or output more than once",
+                    wire.0
+                )));
+            }
+            ids[wire.0] = Some(next_id);
+            next_id += 1;
+            Ok(())
+        };
+        for wire in inputs.iter() {
+            assign(&mut ids, *wire)?;
+        }
+        let intermediate: Vec<usize> = (0..self.get_wire_count())
+            .filter(|w| used[*w] && !outputs.contains(&WireId(*w)) && ids[*w].is_none())
+            .collect();
+        for w in intermediate {
+            assign(&mut ids, WireId(w))?;
+        }
+        for wire in outputs.iter() {
+            assign(&mut ids, *wire)?;
+        }
+
+        writeln!(writer, "{} {}", gates.len(), next_id)?;
+        writeln!(writer, "1 {}", inputs.len())?;
+        writeln!(writer, "1 {}", outputs.len())?;
+        writeln!(writer)?;
+        for id in order {
+            let gate = &gates[id];
+            let inputs = gate.get_inputs();
+            let mnemonic = match gate.gate_type() {
+                GateType::Add | GateType::Sub => "XOR",
+                // ScalarMul never gets here, since its scalar is a constant wire
+                GateType::Mul | GateType::ScalarMul => "AND",
+                GateType::Neg => unreachable!("gates with 1 input are rejected above"),
+            };
+            // every wire of a gate is used, so it has a new id
+            writeln!(
+                writer,
+                "2 1 {} {} {} {}",
+                ids[inputs[0].0].unwrap(),
+                ids[inputs[1].0].unwrap(),
+                ids[gate.get_output().0].unwrap(),
+                mnemonic
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn parse_fixture() {
+        // 2-bit adder dropping the carry out
+        let text = include_str!("../fixtures/adder_2bit.txt");
+        let circuit = Circuit::from_bristol(text.as_bytes()).unwrap();
+
+        assert_eq!(circuit.get_gate_count(), 4);
+        assert_eq!(circuit.get_wire_count(), 8);
+        assert_eq!(
+            circuit.get_all_inputs(),
+            &[WireId(0), WireId(1), WireId(2), WireId(3)]
+        );
+        assert_eq!(circuit.get_all_outputs(), &[WireId(6), WireId(7)]);
+
+        let gate_types: Vec<GateType> = circuit.gate_stream().map(|g| g.gate_type()).collect();
+        assert_eq!(
+            gate_types,
+            vec![GateType::Add, GateType::Mul, GateType::Add, GateType::Add]
+        );
+    }
+
+    #[test]
+    fn unknown_gate_is_rejected() {
+        let text = "1 3\n1 1\n1 1\n\n1 1 0 2 INV\n";
+
+        assert!(matches!(
+            Circuit::from_bristol(text.as_bytes()),
+            Err(CircuitError::BristolParse { line: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        let mut text = vec![];
+        circuit.to_bristol(&mut text).unwrap();
+        let restored = Circuit::from_bristol(&text[..]).unwrap();
+
+        assert_eq!(restored.get_gate_count(), 2);
+        assert_eq!(restored.get_all_inputs().len(), 3);
+        assert_eq!(restored.get_all_outputs().len(), 2);
+        for inputs in [[1, 2, 3], [4, 5, 6], [0, 7, 9]] {
+            let inputs: Vec<Fp> = inputs.into_iter().map(Fp::from).collect();
+            assert_eq!(
+                eval_local(&restored, inputs.clone()),
+                eval_local(&circuit, inputs)
+            );
+        }
+    }
+}
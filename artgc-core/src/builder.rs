@@ -0,0 +1,309 @@
+//! Fluent construction of circuits.
+//! Gates allocate their own output wires, so circuits can be written as nested expressions:
+//! `let z = b.mul(b.add(x, y), w);`
+//!
+//! The `circuit!` macro goes one step further and takes the expressions themselves:
+//! `circuit! { let z = (x + y) * w; output z; }`
+
+use crate::circuit::{Circuit, GateType, WireId};
+use crate::error::CircuitResult;
+use crate::ring::Ring;
+use alloc::collections::BTreeMap;
+use core::cell::RefCell;
+use core::ops::{Add, Mul, Sub};
+
+/// Builds a `Circuit` without manual wire bookkeeping.
+/// All the methods take `&self`, so that calls can be nested in each other's arguments.
+pub struct CircuitBuilder {
+    circuit: RefCell<Circuit>,
+}
+
+impl CircuitBuilder {
+    pub fn new() -> Self {
+        CircuitBuilder {
+            circuit: RefCell::new(Circuit::new()),
+        }
+    }
+
+    /// Create a new input wire.
+    pub fn input(&self) -> WireId {
+        self.circuit.borrow_mut().add_input()
+    }
+
+    /// Create a new wire carrying the fixed value `value`.
+    pub fn constant<T: Ring>(&self, value: T) -> WireId {
+        self.circuit.borrow_mut().create_constant_wire(value)
+    }
+
+    /// Create an Add gate and return its output wire.
+    pub fn add(&self, x: WireId, y: WireId) -> WireId {
+        self.gate(GateType::Add, x, y)
+    }
+
+    /// Create a Sub gate computing `x - y` and return its output wire.
+    pub fn sub(&self, x: WireId, y: WireId) -> WireId {
+        self.gate(GateType::Sub, x, y)
+    }
+
+    /// Create a Mul gate and return its output wire.
+    pub fn mul(&self, x: WireId, y: WireId) -> WireId {
+        self.gate(GateType::Mul, x, y)
+    }
+
+    /// Mark a wire as an output of the circuit. See `Circuit::mark_output`.
+    pub fn output(&self, wire_id: WireId) -> CircuitResult<()> {
+        self.circuit.borrow_mut().mark_output(wire_id)
+    }
+
+    fn gate(&self, gate_type: GateType, x: WireId, y: WireId) -> WireId {
+        let mut circuit = self.circuit.borrow_mut();
+        let out = circuit.create_new_wire();
+        circuit.add_gate(gate_type, x, y, out);
+        out
+    }
+
+    /// Finish building. Returns an error if the circuit is not valid, see `Circuit::is_valid`.
+    pub fn build(self) -> CircuitResult<Circuit> {
+        let circuit = self.circuit.into_inner();
+        circuit.is_valid()?;
+        Ok(circuit)
+    }
+}
+
+impl Default for CircuitBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wire of a circuit being built, whose arithmetic operators create gates.
+/// `x + y` is the same as `b.add(x, y)`.
+#[derive(Clone, Copy)]
+pub struct BuilderWire<'a> {
+    builder: &'a CircuitBuilder,
+    wire_id: WireId,
+}
+
+impl<'a> BuilderWire<'a> {
+    pub fn new(builder: &'a CircuitBuilder, wire_id: WireId) -> Self {
+        BuilderWire { builder, wire_id }
+    }
+
+    pub fn wire_id(&self) -> WireId {
+        self.wire_id
+    }
+}
+
+impl<'a> Add for BuilderWire<'a> {
+    type Output = BuilderWire<'a>;
+
+    fn add(self, rhs: Self) -> Self {
+        BuilderWire::new(self.builder, self.builder.add(self.wire_id, rhs.wire_id))
+    }
+}
+
+impl<'a> Sub for BuilderWire<'a> {
+    type Output = BuilderWire<'a>;
+
+    fn sub(self, rhs: Self) -> Self {
+        BuilderWire::new(self.builder, self.builder.sub(self.wire_id, rhs.wire_id))
+    }
+}
+
+impl<'a> Mul for BuilderWire<'a> {
+    type Output = BuilderWire<'a>;
+
+    fn mul(self, rhs: Self) -> Self {
+        BuilderWire::new(self.builder, self.builder.mul(self.wire_id, rhs.wire_id))
+    }
+}
+
+/// Names of the wires of a `circuit!` invocation.
+#[doc(hidden)]
+pub struct MacroScope<'a> {
+    builder: &'a CircuitBuilder,
+    names: RefCell<BTreeMap<&'static str, WireId>>,
+}
+
+#[doc(hidden)]
+impl<'a> MacroScope<'a> {
+    pub fn new(builder: &'a CircuitBuilder) -> Self {
+        MacroScope {
+            builder,
+            names: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the wire named `name`. A name which is not bound yet becomes a new input.
+    pub fn var(&self, name: &'static str) -> BuilderWire<'a> {
+        let wire_id = *self
+            .names
+            .borrow_mut()
+            .entry(name)
+            .or_insert_with(|| self.builder.input());
+        BuilderWire::new(self.builder, wire_id)
+    }
+
+    pub fn bind(&self, name: &'static str, wire: BuilderWire<'a>) {
+        self.names.borrow_mut().insert(name, wire.wire_id());
+    }
+
+    pub fn output(&self, name: &'static str) {
+        let wire_id = self.var(name).wire_id();
+        self.builder
+            .output(wire_id)
+            .expect("wires of the builder exist");
+    }
+}
+
+/// Build a circuit from arithmetic statements, and return it like `CircuitBuilder::build`.
+///
+/// - `let name = expression;` binds `name` to the output wire of the expression.
+///   Expressions consist of names, `+`, `-`, `*` and parentheses, with the usual precedence.
+/// - `output name;` marks the wire as an output. Outputs keep the order of the statements.
+///
+/// A name used before it is bound becomes an input. Inputs are ordered by their first use.
+/// Names only exist inside the macro, so they don't clash with variables around it.
+///
+/// ```ignore
+/// // inputs x, y and w
+/// let circuit = circuit! {
+///     let z = (x + y) * w;
+///     output z;
+/// }?;
+/// ```
+#[macro_export]
+macro_rules! circuit {
+    ($($body:tt)*) => {{
+        let builder = $crate::builder::CircuitBuilder::new();
+        {
+            let scope = $crate::builder::MacroScope::new(&builder);
+            $crate::__circuit_statements!(scope $($body)*);
+        }
+        builder.build()
+    }};
+}
+
+/// Statements of `circuit!`. The expression of a `let` statement is collected token by token up to its `;`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __circuit_statements {
+    (@let $scope:ident $name:ident [$($expr:tt)*] ; $($rest:tt)*) => {
+        $scope.bind(::core::stringify!($name), $crate::__circuit_expr!($scope [] $($expr)*));
+        $crate::__circuit_statements!($scope $($rest)*);
+    };
+    (@let $scope:ident $name:ident [$($expr:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__circuit_statements!(@let $scope $name [$($expr)* $next] $($rest)*);
+    };
+    ($scope:ident) => {};
+    ($scope:ident let $name:ident = $($rest:tt)*) => {
+        $crate::__circuit_statements!(@let $scope $name [] $($rest)*);
+    };
+    ($scope:ident output $name:ident ; $($rest:tt)*) => {
+        $scope.output(::core::stringify!($name));
+        $crate::__circuit_statements!($scope $($rest)*);
+    };
+}
+
+/// Expression of `circuit!`, with every name replaced by its wire.
+/// Operators are kept as they are, so Rust applies its own precedence to `BuilderWire`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __circuit_expr {
+    ($scope:ident [$($out:tt)*]) => {
+        $($out)*
+    };
+    ($scope:ident [$($out:tt)*] $name:ident $($rest:tt)*) => {
+        $crate::__circuit_expr!($scope [$($out)* $scope.var(::core::stringify!($name))] $($rest)*)
+    };
+    ($scope:ident [$($out:tt)*] ($($inner:tt)*) $($rest:tt)*) => {
+        $crate::__circuit_expr!($scope [$($out)* ($crate::__circuit_expr!($scope [] $($inner)*))] $($rest)*)
+    };
+    ($scope:ident [$($out:tt)*] + $($rest:tt)*) => {
+        $crate::__circuit_expr!($scope [$($out)* +] $($rest)*)
+    };
+    ($scope:ident [$($out:tt)*] - $($rest:tt)*) => {
+        $crate::__circuit_expr!($scope [$($out)* -] $($rest)*)
+    };
+    ($scope:ident [$($out:tt)*] * $($rest:tt)*) => {
+        $crate::__circuit_expr!($scope [$($out)* *] $($rest)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CircuitBuilder;
+    use crate::error::CircuitError;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn build_nested_expression() {
+        let b = CircuitBuilder::new();
+        let x = b.input();
+        let y = b.input();
+        let w = b.input();
+        let z = b.mul(b.add(x, y), w);
+        b.output(z).unwrap();
+
+        let circuit = b.build().unwrap();
+        assert_eq!(circuit.get_gate_count(), 2);
+
+        let inputs = [1, 2, 3].map(Fp::from).to_vec();
+        assert_eq!(eval_local(&circuit, inputs), Ok(alloc::vec![Fp::from(9)]));
+    }
+
+    #[test]
+    fn macro_matches_hand_built_circuit() {
+        let circuit = circuit! {
+            let z = (x + y) * w;
+            output z;
+        }
+        .unwrap();
+
+        let b = CircuitBuilder::new();
+        let x = b.input();
+        let y = b.input();
+        let w = b.input();
+        let z = b.mul(b.add(x, y), w);
+        b.output(z).unwrap();
+        let expected = b.build().unwrap();
+
+        assert_eq!(circuit.get_gate_count(), expected.get_gate_count());
+        let inputs = [1, 2, 3].map(Fp::from).to_vec();
+        assert_eq!(
+            eval_local(&circuit, inputs.clone()),
+            eval_local(&expected, inputs)
+        );
+    }
+
+    #[test]
+    fn macro_with_several_outputs() {
+        // x is an input of the macro, not this variable
+        let x = 100;
+        let circuit = circuit! {
+            let s = x + y * x;
+            let d = (x - y) * (s + y);
+            output s;
+            output d;
+        }
+        .unwrap();
+        assert_eq!(x, 100);
+
+        // s = 2 + 3 * 2 = 8, d = (2 - 3) * (8 + 3) = -11
+        let inputs = [2, 3].map(Fp::from).to_vec();
+        assert_eq!(
+            eval_local(&circuit, inputs),
+            Ok(alloc::vec![Fp::from(8), Fp::from(0) - Fp::from(11)])
+        );
+    }
+
+    #[test]
+    fn build_rejects_invalid_circuit() {
+        let b = CircuitBuilder::new();
+        let x = b.input();
+        b.add(x, x);
+
+        assert_eq!(b.build().err(), Some(CircuitError::EmptyOutput));
+    }
+}
@@ -2,19 +2,36 @@
 //! The following types of wires and gates shape DAGs as its nodes.
 //! Interemediate wires act as edges of the DAG.
 //!
-//! Input wire: Starting nodes. Carry a single value. Either Variable or Constant in F
+//! Input wire: Starting nodes. Carry a single value given at evaluation time.
+//! Constant wire: Starting nodes. Carry a fixed value stored in the circuit.
 //! Output wire: Ending nodes.
 //! Add gate: Two input, one output. Calculate addition of two input values.
+//! Sub gate: Two input, one output. Calculate subtraction of the second input value from the first one.
 //! Mul gate: Two input, one output. Calculate multiplication of two input values.
+//! Neg gate: One input, one output. Calculate additive inverse of the input value.
+//! ScalarMul gate: Input and constant scalar wire, one output. Calculate multiplication of the input value by the scalar.
 
-use crate::error::{CircuitError, CircuitResult};
+use crate::detect_cycle::detect_cycle;
+use crate::error::{Capacity, CircuitError, CircuitResult};
+use crate::eval_local::{label_wires_with_layer, Layers};
+use crate::ring::Ring;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+#[cfg(test)]
+use core::cell::Cell;
+use core::cell::{Ref, RefCell};
 
 /// Wire is a representation of a value carrier in garbled circuit.
 /// It does not carry a value directly. Rather, it has encoded representation of the value called label.
 /// In this specific instance of wire, we only have an id so that the two party can agree on the structure of
 /// the circuit they are talking about.
-// TODO: have a hashability by adding Derive serde
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Wire ids are ordered by their number, so they can be keys of ordered maps as well as hash maps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WireId(pub usize);
 
 impl From<usize> for WireId {
@@ -35,7 +52,12 @@ impl<'a> Into<usize> for &'a WireId {
     }
 }
 
+/// Id of a gate. It is the index of the gate in the circuit's gate list.
+pub type GateId = usize;
+
 /// A gate has id, input x, input y and out as members.
+/// `AddMany` sums any number of input wires, instead of chaining binary `Add` gates.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Gate {
     Add {
         id: usize,
@@ -43,69 +65,393 @@ pub enum Gate {
         y: WireId,
         out: WireId,
     },
+    Sub {
+        id: usize,
+        x: WireId,
+        y: WireId,
+        out: WireId,
+    },
     Mul {
         id: usize,
         x: WireId,
         y: WireId,
         out: WireId,
     },
+    AddMany {
+        id: usize,
+        inputs: Vec<WireId>,
+        out: WireId,
+    },
+    Neg {
+        id: usize,
+        x: WireId,
+        out: WireId,
+    },
+    /// Multiplication by a public value, stored in the constant wire `scalar`.
+    ScalarMul {
+        id: usize,
+        x: WireId,
+        scalar: WireId,
+        out: WireId,
+    },
 }
 
 impl Gate {
+    /// Id of this gate, which is its position in the gate list of its circuit.
+    pub fn id(&self) -> GateId {
+        match self {
+            Gate::Add { id, .. }
+            | Gate::Sub { id, .. }
+            | Gate::Mul { id, .. }
+            | Gate::AddMany { id, .. }
+            | Gate::Neg { id, .. }
+            | Gate::ScalarMul { id, .. } => *id,
+        }
+    }
+
     pub fn get_output(&self) -> WireId {
         match self {
             Gate::Add { out, .. } => *out,
+            Gate::Sub { out, .. } => *out,
             Gate::Mul { out, .. } => *out,
+            Gate::AddMany { out, .. } => *out,
+            Gate::Neg { out, .. } => *out,
+            Gate::ScalarMul { out, .. } => *out,
         }
     }
 
-    pub fn get_inputs(&self) -> (WireId, WireId) {
+    /// Input wires of this gate in operand order. A wire read twice (x * x) appears twice.
+    pub fn get_inputs(&self) -> Vec<WireId> {
         match self {
-            Gate::Add { x, y, .. } => (*x, *y),
-            Gate::Mul { x, y, .. } => (*x, *y),
+            Gate::Add { x, y, .. } => vec![*x, *y],
+            Gate::Sub { x, y, .. } => vec![*x, *y],
+            Gate::Mul { x, y, .. } => vec![*x, *y],
+            Gate::AddMany { inputs, .. } => inputs.clone(),
+            Gate::Neg { x, .. } => vec![*x],
+            Gate::ScalarMul { x, scalar, .. } => vec![*x, *scalar],
+        }
+    }
+
+    /// Number of wire reads of this gate. A wire read twice (x * x) is counted twice.
+    pub fn arity(&self) -> usize {
+        match self {
+            Gate::Add { .. } | Gate::Sub { .. } | Gate::Mul { .. } | Gate::ScalarMul { .. } => 2,
+            Gate::AddMany { inputs, .. } => inputs.len(),
+            Gate::Neg { .. } => 1,
+        }
+    }
+
+    /// Compute the output value of this gate from the values of its input wires, in the order of `get_inputs`.
+    pub(crate) fn evaluate<T: Ring>(&self, values: impl IntoIterator<Item = T>) -> T {
+        let mut values = values.into_iter();
+        // every gate has at least one input
+        let first = values.next().unwrap();
+        match self.gate_type() {
+            GateType::Add => values.fold(first, |sum, value| sum + value),
+            GateType::Sub => first - values.next().unwrap(),
+            GateType::Mul | GateType::ScalarMul => first * values.next().unwrap(),
+            GateType::Neg => T::zero() - first,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GateType {
     Add,
+    Sub,
     Mul,
+    Neg,
+    ScalarMul,
+}
+
+impl From<&Gate> for GateType {
+    fn from(gate: &Gate) -> Self {
+        gate.gate_type()
+    }
+}
+
+impl From<&Gate> for GateId {
+    fn from(gate: &Gate) -> Self {
+        gate.id()
+    }
 }
 
 impl Gate {
     pub fn gate_type(&self) -> GateType {
         match self {
             Gate::Add { .. } => GateType::Add,
+            Gate::Sub { .. } => GateType::Sub,
             Gate::Mul { .. } => GateType::Mul,
+            // n-ary addition is still an addition
+            Gate::AddMany { .. } => GateType::Add,
+            Gate::Neg { .. } => GateType::Neg,
+            Gate::ScalarMul { .. } => GateType::ScalarMul,
         }
     }
+
+    /// Returns a copy of the gate with id `id` and every wire replaced by `map(wire)`.
+    pub(crate) fn remapped(&self, id: usize, map: impl Fn(WireId) -> WireId) -> Gate {
+        match self {
+            Gate::Add { x, y, out, .. } => Gate::Add {
+                id,
+                x: map(*x),
+                y: map(*y),
+                out: map(*out),
+            },
+            Gate::Sub { x, y, out, .. } => Gate::Sub {
+                id,
+                x: map(*x),
+                y: map(*y),
+                out: map(*out),
+            },
+            Gate::Mul { x, y, out, .. } => Gate::Mul {
+                id,
+                x: map(*x),
+                y: map(*y),
+                out: map(*out),
+            },
+            Gate::AddMany { inputs, out, .. } => Gate::AddMany {
+                id,
+                inputs: inputs.iter().map(|input| map(*input)).collect(),
+                out: map(*out),
+            },
+            Gate::Neg { x, out, .. } => Gate::Neg {
+                id,
+                x: map(*x),
+                out: map(*out),
+            },
+            Gate::ScalarMul { x, scalar, out, .. } => Gate::ScalarMul {
+                id,
+                x: map(*x),
+                scalar: map(*scalar),
+                out: map(*out),
+            },
+        }
+    }
+}
+
+/// Wire bound to a fixed value.
+/// The value is stored without its ring type, so that `Circuit` doesn't have to be generic over the ring.
+pub struct Constant {
+    wire_id: WireId,
+    value: Box<dyn Any + Send + Sync>,
+    // clones `value`, which only works through its original type
+    clone_value: fn(&(dyn Any + Send + Sync)) -> Box<dyn Any + Send + Sync>,
+    // formats `value` with its ring type, which only works through its original type
+    describe_value: fn(&(dyn Any + Send + Sync)) -> String,
+}
+
+fn clone_value<T: Ring>(value: &(dyn Any + Send + Sync)) -> Box<dyn Any + Send + Sync> {
+    Box::new(value.downcast_ref::<T>().unwrap().clone())
+}
+
+fn describe_value<T: Ring>(value: &(dyn Any + Send + Sync)) -> String {
+    alloc::format!(
+        "{}: {:?}",
+        core::any::type_name::<T>(),
+        value.downcast_ref::<T>().unwrap()
+    )
+}
+
+impl Constant {
+    pub fn get_wire_id(&self) -> WireId {
+        self.wire_id
+    }
+
+    /// Returns the value of the constant, or None if it was created with a ring other than `T`.
+    pub fn get_value<T: Ring>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+
+    /// Returns a constant with the same value on wire `wire_id`.
+    pub(crate) fn clone_to(&self, wire_id: WireId) -> Constant {
+        Constant {
+            wire_id,
+            value: (self.clone_value)(self.value.as_ref()),
+            clone_value: self.clone_value,
+            describe_value: self.describe_value,
+        }
+    }
+
+    /// Returns the ring type and the `Debug` representation of the value,
+    /// which are equal exactly when the values are, for the rings in this crate.
+    #[cfg(feature = "std")]
+    pub(crate) fn describe_value(&self) -> String {
+        (self.describe_value)(self.value.as_ref())
+    }
+}
+
+/// Gates connected to a wire, see `Circuit::wire_connections`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WireConnection {
+    /// Ids of the gates which read this wire, in gate id order.
+    /// A gate reading the wire as both of its inputs is listed twice.
+    pub to_ids: Vec<usize>,
+    /// Id of the gate which this wire comes out of.
+    /// Input wires, constant wires and undriven wires have None.
+    /// If several gates drive the wire, this is the last of them.
+    pub from_id: Option<usize>,
+}
+
+impl WireConnection {
+    /// Ids of the gates which read this wire, in gate id order, each listed once.
+    pub fn distinct_to_ids(&self) -> Vec<usize> {
+        let mut ids = self.to_ids.clone();
+        ids.dedup();
+        ids
+    }
 }
 
 pub struct Circuit {
     inputs: Vec<WireId>,
+    constants: Vec<Constant>,
     outputs: Vec<WireId>,
     gates: Vec<Gate>,
     wire_count: usize,
+    wire_offset: usize,
     gate_count: usize,
+    // BTreeMap instead of HashMap, so that names are available without std
+    input_names: BTreeMap<String, WireId>,
+    output_names: BTreeMap<String, WireId>,
+    // whether `try_add_gate` checks for cycles, see `with_cycle_guard`
+    cycle_guard: bool,
+    // caps on the number of wires, not counting the offset, and of gates. See `with_capacity_limits`.
+    max_wires: Option<usize>,
+    max_gates: Option<usize>,
+    // layering of the current structure, see `layers`. Cleared by every mutation.
+    // RefCell makes Circuit !Sync, so a circuit can't be shared between threads by reference.
+    layer_cache: RefCell<Option<Layers>>,
+    // number of times the layers have been computed, to check that the cache is hit
+    #[cfg(test)]
+    pub(crate) layer_computations: Cell<usize>,
+}
+
+/// Returns `CircuitError::ArityMismatch` if `gate_type` is not the type of a gate with two inputs,
+/// which `add_gate` panics on.
+pub(crate) fn check_binary(gate_type: GateType) -> CircuitResult<()> {
+    match gate_type {
+        GateType::Add | GateType::Sub | GateType::Mul | GateType::ScalarMul => Ok(()),
+        GateType::Neg => Err(CircuitError::ArityMismatch { gate_type, got: 2 }),
+    }
 }
 
 impl Circuit {
-    // TODO: should make CircuitBuilder to do circuit construction
+    /// Create an empty circuit.
+    /// See `CircuitBuilder` for building circuits without manual wire bookkeeping.
     pub fn new() -> Self {
         Circuit {
             inputs: vec![],
+            constants: vec![],
             outputs: vec![],
             gates: vec![],
 
             wire_count: 0,
+            wire_offset: 0,
             gate_count: 0,
+
+            input_names: BTreeMap::new(),
+            output_names: BTreeMap::new(),
+            cycle_guard: false,
+            max_wires: None,
+            max_gates: None,
+
+            layer_cache: RefCell::new(None),
+            #[cfg(test)]
+            layer_computations: Cell::new(0),
+        }
+    }
+
+    /// Build a circuit with wires 0 to `wire_count` - 1 in one call, for parsers and generators
+    /// which already have the full gate list.
+    /// Gates get sequential ids in the order of `gates`, each given as (gate type, x, y, out).
+    /// A Neg gate only reads x, and its y is ignored.
+    ///
+    /// Returns `CircuitError::UnknownWire` if a gate, an input or an output refers to a wire `>= wire_count`,
+    /// and otherwise the errors of `mark_input` and `is_valid`.
+    pub fn from_parts(
+        wire_count: usize,
+        gates: Vec<(GateType, WireId, WireId, WireId)>,
+        inputs: Vec<WireId>,
+        outputs: Vec<WireId>,
+    ) -> CircuitResult<Circuit> {
+        let mut circuit = Circuit::new();
+        circuit.wire_count = wire_count;
+
+        for (gate_type, x, y, out) in gates {
+            for wire in [x, y, out] {
+                circuit.check_wire(wire)?;
+            }
+            match gate_type {
+                GateType::Neg => {
+                    let id = circuit.gate_count;
+                    circuit.push_gate(Gate::Neg { id, x, out });
+                }
+                _ => {
+                    circuit.add_gate(gate_type, x, y, out);
+                }
+            }
+        }
+        for input in inputs {
+            circuit.mark_input(input)?;
+        }
+        for output in outputs {
+            circuit.mark_output(output)?;
+        }
+
+        circuit.is_valid()?;
+        Ok(circuit)
+    }
+
+    /// Create an empty circuit whose wire ids start at `offset` instead of 0,
+    /// so that it can share a wire id namespace with other circuits.
+    /// Ids below `offset` are reserved and never belong to this circuit.
+    pub fn with_wire_offset(offset: usize) -> Self {
+        Circuit {
+            wire_count: offset,
+            wire_offset: offset,
+            ..Circuit::new()
         }
     }
 
+    /// Create an empty circuit on which `try_add_gate` rejects gates closing a cycle.
+    /// `add_gate` and the other ways of adding gates stay unchecked.
+    pub fn with_cycle_guard() -> Self {
+        Circuit {
+            cycle_guard: true,
+            ..Circuit::new()
+        }
+    }
+
+    /// Create an empty circuit which can have at most `max_wires` wires and `max_gates` gates,
+    /// so that a runaway generator fails instead of exhausting memory.
+    /// `try_create_new_wire` and `try_add_gate` return `CircuitError::CapacityExceeded` past the limits,
+    /// and every other method adding a wire or a gate panics.
+    pub fn with_capacity_limits(max_wires: usize, max_gates: usize) -> Self {
+        Circuit {
+            max_wires: Some(max_wires),
+            max_gates: Some(max_gates),
+            ..Circuit::new()
+        }
+    }
+
+    /// Whether this circuit was created by `with_cycle_guard`.
+    pub fn has_cycle_guard(&self) -> bool {
+        self.cycle_guard
+    }
+
+    /// Returns one past the largest wire id of this circuit.
+    /// This is the number of wires plus the wire offset,
+    /// so vectors indexed by wire id can be sized with it.
     pub fn get_wire_count(&self) -> usize {
         self.wire_count
     }
 
+    /// Returns the smallest wire id of this circuit.
+    pub fn get_wire_offset(&self) -> usize {
+        self.wire_offset
+    }
+
     pub fn get_gate_count(&self) -> usize {
         self.gate_count
     }
@@ -114,6 +460,87 @@ impl Circuit {
         &self.gates
     }
 
+    /// Returns the layers of gates and wires, see `Layers`.
+    /// They are computed on the first call and cached until the circuit is modified,
+    /// so repeated evaluations of the same circuit don't scan it again.
+    ///
+    /// Panics if the returned reference is still alive when the layers are requested
+    /// again after the cache was cleared, which can't happen without mutating the circuit.
+    pub fn layers(&self) -> Ref<'_, Layers> {
+        if self.layer_cache.borrow().is_none() {
+            #[cfg(test)]
+            self.layer_computations
+                .set(self.layer_computations.get() + 1);
+            *self.layer_cache.borrow_mut() = Some(label_wires_with_layer(self));
+        }
+        Ref::map(self.layer_cache.borrow(), |layers| {
+            layers.as_ref().expect("layers were just computed")
+        })
+    }
+
+    /// Clear the cached layers. Called by every method changing gates, wires, inputs or constants.
+    fn invalidate_layers(&mut self) {
+        *self.layer_cache.get_mut() = None;
+    }
+
+    /// Iterate over all the gates in id order.
+    /// Code which only needs a single pass over the gates should prefer this to `get_all_gates`,
+    /// so that it doesn't depend on gates being held in memory.
+    pub fn gate_stream(&self) -> impl Iterator<Item = &Gate> {
+        self.gates.iter()
+    }
+
+    /// Returns a copy of this circuit with every wire id, including inputs, outputs, named wires and constants,
+    /// increased by `wire_offset`. The wire offset of the copy grows by the same amount,
+    /// so copies made with different offsets have disjoint wire ids.
+    /// Gate ids are positions in the gate list, so they are unchanged. `merge` renumbers them
+    /// when a gadget is added to a larger circuit.
+    pub fn clone_remapped(&self, wire_offset: usize) -> Circuit {
+        let map = |wire: WireId| WireId(wire.0 + wire_offset);
+        let map_names = |names: &BTreeMap<String, WireId>| {
+            names
+                .iter()
+                .map(|(name, wire)| (name.clone(), map(*wire)))
+                .collect()
+        };
+        Circuit {
+            inputs: self.inputs.iter().map(|wire| map(*wire)).collect(),
+            constants: self
+                .constants
+                .iter()
+                .map(|constant| constant.clone_to(map(constant.wire_id)))
+                .collect(),
+            outputs: self.outputs.iter().map(|wire| map(*wire)).collect(),
+            gates: self
+                .gates
+                .iter()
+                .map(|gate| gate.remapped(gate.id(), map))
+                .collect(),
+            wire_count: self.wire_count + wire_offset,
+            wire_offset: self.wire_offset + wire_offset,
+            gate_count: self.gate_count,
+            input_names: map_names(&self.input_names),
+            output_names: map_names(&self.output_names),
+            cycle_guard: self.cycle_guard,
+            max_wires: self.max_wires,
+            max_gates: self.max_gates,
+            ..Circuit::new()
+        }
+    }
+
+    /// Returns the gates reading and driving each wire, indexed by wire id.
+    pub fn wire_connections(&self) -> Vec<WireConnection> {
+        let mut wire_connections = vec![WireConnection::default(); self.wire_count];
+        for gate in self.gate_stream() {
+            let id = gate.id();
+            for input in gate.get_inputs() {
+                wire_connections[input.0].to_ids.push(id);
+            }
+            wire_connections[gate.get_output().0].from_id = Some(id)
+        }
+        wire_connections
+    }
+
     pub fn get_gate(&self, id: usize) -> Option<&Gate> {
         self.gates.get(id)
     }
@@ -122,6 +549,10 @@ impl Circuit {
         &self.inputs
     }
 
+    pub fn get_constants(&self) -> &[Constant] {
+        &self.constants
+    }
+
     pub fn get_all_outputs(&self) -> &[WireId] {
         &self.outputs
     }
@@ -131,24 +562,76 @@ impl Circuit {
     /// 1. Input length must be greater than 0.
     /// 2. Output length must be greater than 0.
     /// 3. All wires have to be connected to some other wires.
-    /// 4. All wires have to have a path to at least one output wire.
+    /// 4. All wires have to have a path to at least one output wire. Input wires are exempt.
+    /// 5. There is no cyclic path.
     pub fn is_valid(&self) -> CircuitResult<()> {
         if self.inputs.is_empty() {
             return Err(CircuitError::EmptyInput);
         } else if self.outputs.is_empty() {
             return Err(CircuitError::EmptyOutput);
-            // } else if let Some(cycles) = check_cycles(self) {
-            //     return Err();
+        }
+
+        // rule 3: every wire is an input, an output, a constant or a part of some gate
+        let mut connected = vec![false; self.wire_count];
+        let gate_wires = self.gates.iter().flat_map(|gate| {
+            let mut wires = gate.get_inputs();
+            wires.push(gate.get_output());
+            wires
+        });
+        let constants = self.constants.iter().map(|constant| constant.wire_id);
+        let used_wires = self.inputs.iter().chain(self.outputs.iter()).copied();
+        for wire in used_wires.chain(constants).chain(gate_wires) {
+            // wires below the offset are reserved and don't belong to this circuit
+            self.check_wire(wire)?;
+            connected[wire.0] = true;
+        }
+        if let Some(wire_id) = (self.wire_offset..self.wire_count).find(|w| !connected[*w]) {
+            return Err(CircuitError::DanglingWire { wire_id });
+        }
+
+        // every wire id is known at this point, so the cycle search can index by them
+        if let Some((gate_id, wire_id)) = detect_cycle(self) {
+            return Err(CircuitError::CyclicPath {
+                gate_id,
+                wire_id: wire_id.0,
+            });
+        }
+
+        // rule 4: walk backward from the outputs through the gates producing each wire.
+        // input wires are a part of the interface of the circuit, so they are allowed to be unused.
+        let mut producers: Vec<Option<&Gate>> = vec![None; self.wire_count];
+        for gate in self.gates.iter() {
+            producers[gate.get_output().0] = Some(gate);
+        }
+        let mut reaches_output = vec![false; self.wire_count];
+        let mut stack: Vec<usize> = self.outputs.iter().map(|w| w.0).collect();
+        while let Some(w) = stack.pop() {
+            if reaches_output[w] {
+                continue;
+            }
+            reaches_output[w] = true;
+            if let Some(gate) = producers[w] {
+                stack.extend(gate.get_inputs().iter().map(|w| w.0));
+            }
+        }
+        for input in self.inputs.iter() {
+            reaches_output[input.0] = true;
+        }
+        if let Some(wire_id) = (self.wire_offset..self.wire_count).find(|w| !reaches_output[*w]) {
+            return Err(CircuitError::UnreachableWire { wire_id });
         }
 
         Ok(())
     }
 
     /// Create a gate and add it to circuit
-    /// gate_type: Type of Gate. GateType::Add or GateType::Mul
+    /// gate_type: Type of Gate. GateType::Add, GateType::Sub, GateType::Mul or GateType::ScalarMul
     /// x_id: wire id of the first input of the gate
-    /// y_id: wire id of the second input of the gate
+    /// y_id: wire id of the second input of the gate. It must be a constant wire for GateType::ScalarMul
     /// out_id: id of the wire of output from this gate
+    ///
+    /// Panics if `gate_type` is `GateType::Neg`, which has a single input. Use `add_neg` instead.
+    /// Also panics if the circuit already has as many gates as it can, see `try_add_gate`.
     pub fn add_gate(
         &mut self,
         gate_type: GateType,
@@ -164,38 +647,495 @@ impl Circuit {
                 y: y_id,
                 out: out_id,
             },
+            GateType::Sub => Gate::Sub {
+                id,
+                x: x_id,
+                y: y_id,
+                out: out_id,
+            },
             GateType::Mul => Gate::Mul {
                 id,
                 x: x_id,
                 y: y_id,
                 out: out_id,
             },
+            GateType::ScalarMul => Gate::ScalarMul {
+                id,
+                x: x_id,
+                scalar: y_id,
+                out: out_id,
+            },
+            GateType::Neg => panic!("Neg gate has a single input, use add_neg instead"),
         };
-
-        self.gates.push(gate);
-        self.gate_count += 1;
+        self.push_gate(gate);
 
         id
     }
 
+    /// Same as `add_gate`, but rejects a gate whose output wire is also one of its input wires.
+    /// This catches the simplest cycle without running full cycle detection.
+    /// Returns `CircuitError::SelfLoop` with the id the gate would have had,
+    /// `CircuitError::ArityMismatch` if `gate_type` is `GateType::Neg`,
+    /// and `CircuitError::CapacityExceeded` if no more gates can be added.
+    pub fn add_gate_checked(
+        &mut self,
+        gate_type: GateType,
+        x_id: WireId,
+        y_id: WireId,
+        out_id: WireId,
+    ) -> CircuitResult<usize> {
+        check_binary(gate_type)?;
+        self.check_gate_capacity()?;
+        if out_id == x_id || out_id == y_id {
+            return Err(CircuitError::SelfLoop {
+                gate_id: self.gate_count,
+            });
+        }
+        Ok(self.add_gate(gate_type, x_id, y_id, out_id))
+    }
+
+    /// Create a gate summing all of `inputs` at once, and return its new output wire.
+    /// A wire may appear in `inputs` more than once, and is added that many times.
+    ///
+    /// Panics if `inputs` is empty.
+    pub fn add_many(&mut self, inputs: &[WireId]) -> WireId {
+        assert!(!inputs.is_empty(), "add_many needs at least one input");
+
+        let out = self.create_new_wire();
+        self.push_gate(Gate::AddMany {
+            id: self.gate_count,
+            inputs: inputs.to_vec(),
+            out,
+        });
+        out
+    }
+
+    /// Create a gate negating `x`, and return its new output wire.
+    pub fn add_neg(&mut self, x: WireId) -> WireId {
+        let out = self.create_new_wire();
+        self.push_gate(Gate::Neg {
+            id: self.gate_count,
+            x,
+            out,
+        });
+        out
+    }
+
+    /// Create a gate multiplying `x` by the public value `scalar`, and return its new output wire.
+    /// The scalar is stored in a new constant wire, see `create_constant_wire`.
+    pub fn add_scalar_mul<T: Ring>(&mut self, x: WireId, scalar: T) -> WireId {
+        let scalar = self.create_constant_wire(scalar);
+        let out = self.create_new_wire();
+        self.add_gate(GateType::ScalarMul, x, scalar, out);
+        out
+    }
+
+    /// Create a new wire as the output of a gate and mark it as an output of the circuit.
+    /// Returns id of the newly created output wire.
+    ///
+    /// Panics if `gate_type` is `GateType::Neg`, like `add_gate`.
+    pub fn add_output_gate(&mut self, gate_type: GateType, x_id: WireId, y_id: WireId) -> WireId {
+        let out_id = self.create_new_wire();
+        self.add_gate(gate_type, x_id, y_id, out_id);
+        self.outputs.push(out_id);
+        out_id
+    }
+
     /// Create a wire with a given value.
     /// Increment self.wire_len and return the wire instance.
+    ///
+    /// Panics if the circuit already has as many wires as it can, see `try_create_new_wire`.
     pub fn create_new_wire(&mut self) -> WireId {
+        self.try_create_new_wire()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Same as `create_new_wire`, but returns `CircuitError::CapacityExceeded` if the circuit already has
+    /// the number of wires given to `with_capacity_limits`, or if wire ids would overflow.
+    pub fn try_create_new_wire(&mut self) -> CircuitResult<WireId> {
+        let limit = self.max_wires.unwrap_or(usize::MAX - self.wire_offset);
+        if self.wire_count - self.wire_offset >= limit {
+            return Err(CircuitError::CapacityExceeded {
+                capacity: Capacity::Wires,
+                limit,
+            });
+        }
         let wire_id = WireId::from(self.wire_count);
         self.wire_count += 1;
+        self.invalidate_layers();
+        Ok(wire_id)
+    }
+
+    /// Create a new wire and mark it as an input of the circuit.
+    /// Returns id of the newly created input wire.
+    pub fn add_input(&mut self) -> WireId {
+        let wire_id = self.create_new_wire();
+        self.inputs.push(wire_id);
         wire_id
     }
 
-    /// Create a wire instance and push it to the inputs vector.
-    /// Return id of the newly created wire.
-    pub fn mark_input(&mut self, wire_id: WireId) {
+    /// Reduce `leaves` to a single root wire by applying `compress` pairwise, level by level,
+    /// like a Merkle tree. If a level has an odd number of wires, the last one is carried up as it is.
+    /// Returns the root wire, which is the leaf itself if there is only one.
+    ///
+    /// Panics if `leaves` is empty.
+    pub fn compression_tree(
+        &mut self,
+        leaves: &[WireId],
+        compress: impl Fn(&mut Circuit, WireId, WireId) -> WireId,
+    ) -> WireId {
+        assert!(
+            !leaves.is_empty(),
+            "compression tree needs at least one leaf"
+        );
+
+        let mut level = leaves.to_vec();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [x, y] => compress(self, *x, *y),
+                    _ => pair[0],
+                })
+                .collect();
+        }
+        level[0]
+    }
+
+    /// Create a new wire carrying the fixed value `value`.
+    /// Constant wires are not inputs, so they don't take a value from the inputs of evaluation.
+    /// Evaluating the circuit over a ring other than `T` fails, since the constant has no value there.
+    pub fn create_constant_wire<T: Ring>(&mut self, value: T) -> WireId {
+        let wire_id = self.create_new_wire();
+        self.bind_constant(wire_id, value);
+        wire_id
+    }
+
+    /// Make an existing wire carry the fixed value `value`.
+    /// The wire must not be an input, a constant or an output of a gate.
+    pub(crate) fn bind_constant<T: Ring>(&mut self, wire_id: WireId, value: T) {
+        self.constants.push(Constant {
+            wire_id,
+            value: Box::new(value),
+            clone_value: clone_value::<T>,
+            describe_value: describe_value::<T>,
+        });
+        self.invalidate_layers();
+    }
+
+    pub(crate) fn push_constant(&mut self, constant: Constant) {
+        self.constants.push(constant);
+        self.invalidate_layers();
+    }
+
+    /// Returns `CircuitError::CapacityExceeded` if no more gates can be added to the circuit.
+    pub(crate) fn check_gate_capacity(&self) -> CircuitResult<()> {
+        let limit = self.max_gates.unwrap_or(usize::MAX);
+        if self.gate_count >= limit {
+            return Err(CircuitError::CapacityExceeded {
+                capacity: Capacity::Gates,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Add `gate` as is. Its id must be the current gate count.
+    ///
+    /// Panics if no more gates can be added, see `check_gate_capacity`.
+    pub(crate) fn push_gate(&mut self, gate: Gate) {
+        if let Err(error) = self.check_gate_capacity() {
+            panic!("{}", error);
+        }
+        self.gates.push(gate);
+        self.gate_count += 1;
+        self.invalidate_layers();
+    }
+
+    /// Remove the gate with id `gate_id`.
+    /// Gate ids are positions, so ids are compacted: every later gate is renumbered to one id less,
+    /// and ids of gates obtained before are no longer valid.
+    ///
+    /// The output wire of the gate is kept, with no gate driving it, so that inputs, outputs and other gates
+    /// referring to it stay unchanged. It can be reclaimed by adding another gate with it as output wire.
+    /// Until then, evaluating the circuit fails with `EvalLocalError::EmptyWire`.
+    ///
+    /// Returns `CircuitError::UnknownGate` if there is no such gate.
+    pub fn remove_gate(&mut self, gate_id: usize) -> CircuitResult<()> {
+        if gate_id >= self.gates.len() {
+            return Err(CircuitError::UnknownGate { gate_id });
+        }
+        let keep: Vec<bool> = (0..self.gates.len()).map(|id| id != gate_id).collect();
+        self.retain_gates(&keep);
+        Ok(())
+    }
+
+    /// Remove gates whose entry in `keep` is false.
+    /// Remaining gates keep their order and are renumbered, so that gate ids stay equal to positions.
+    pub(crate) fn retain_gates(&mut self, keep: &[bool]) {
+        let gates = core::mem::take(&mut self.gates);
+        for (mut gate, _) in gates.into_iter().zip(keep).filter(|(_, keep)| **keep) {
+            match &mut gate {
+                Gate::Add { id, .. }
+                | Gate::Sub { id, .. }
+                | Gate::Mul { id, .. }
+                | Gate::AddMany { id, .. }
+                | Gate::Neg { id, .. }
+                | Gate::ScalarMul { id, .. } => *id = self.gates.len(),
+            }
+            self.gates.push(gate);
+        }
+        self.gate_count = self.gates.len();
+        self.invalidate_layers();
+    }
+
+    /// Remove wires whose entry in `keep` is false, along with the constants on them.
+    /// They must not be an input, an output or a wire of any gate.
+    /// Remaining wires keep their order and are renumbered from the wire offset, so that wire ids stay dense.
+    #[cfg(feature = "std")]
+    pub(crate) fn retain_wires(&mut self, keep: &[bool]) {
+        let mut new_ids = vec![None; self.wire_count];
+        let mut wire_count = self.wire_offset;
+        for (wire, new_id) in new_ids.iter_mut().enumerate().skip(self.wire_offset) {
+            if keep[wire] {
+                *new_id = Some(WireId(wire_count));
+                wire_count += 1;
+            }
+        }
+        let map = |wire: WireId| new_ids[wire.0].expect("removed wire is still in use");
+
+        for wire in self.inputs.iter_mut().chain(self.outputs.iter_mut()) {
+            *wire = map(*wire);
+        }
+        for wire in self
+            .input_names
+            .values_mut()
+            .chain(self.output_names.values_mut())
+        {
+            *wire = map(*wire);
+        }
+        self.constants.retain(|constant| keep[constant.wire_id.0]);
+        for constant in self.constants.iter_mut() {
+            constant.wire_id = map(constant.wire_id);
+        }
+        self.gates = self
+            .gates
+            .iter()
+            .enumerate()
+            .map(|(id, gate)| gate.remapped(id, map))
+            .collect();
+
+        self.wire_count = wire_count;
+        self.invalidate_layers();
+    }
+
+    /// Returns `CircuitError::UnknownWire` if `wire_id` is not a wire of this circuit.
+    pub(crate) fn check_wire(&self, wire_id: WireId) -> CircuitResult<()> {
+        if wire_id.0 < self.wire_offset || wire_id.0 >= self.wire_count {
+            return Err(CircuitError::UnknownWire { wire_id: wire_id.0 });
+        }
+        Ok(())
+    }
+
+    /// Push an existing wire to the inputs vector.
+    /// Returns `CircuitError::UnknownWire` if the wire has not been created,
+    /// and `CircuitError::DuplicateInput` if it is already an input.
+    pub fn mark_input(&mut self, wire_id: WireId) -> CircuitResult<()> {
+        self.check_wire(wire_id)?;
+        if self.inputs.contains(&wire_id) {
+            return Err(CircuitError::DuplicateInput { wire_id: wire_id.0 });
+        }
         self.inputs.push(wire_id);
+        self.invalidate_layers();
+        Ok(())
     }
 
-    /// Create a wire instance and push it to the outputs vector.
-    /// Return id of the newly created wire.
-    pub fn mark_output(&mut self, wire_id: WireId) {
+    /// Push an existing wire to the outputs vector.
+    /// A wire can be marked as an output more than once, and then it has several output positions.
+    /// Returns `CircuitError::UnknownWire` if the wire has not been created.
+    pub fn mark_output(&mut self, wire_id: WireId) -> CircuitResult<()> {
+        self.check_wire(wire_id)?;
         self.outputs.push(wire_id);
+        self.invalidate_layers();
+        Ok(())
+    }
+
+    /// Same as `mark_input`, but also gives the input the name `name`.
+    /// If another input already has that name, the name is moved to this one.
+    pub fn mark_input_named(&mut self, wire_id: WireId, name: &str) -> CircuitResult<()> {
+        self.mark_input(wire_id)?;
+        self.input_names.insert(name.into(), wire_id);
+        Ok(())
+    }
+
+    /// Same as `mark_output`, but also gives the output the name `name`.
+    /// If another output already has that name, the name is moved to this one.
+    pub fn mark_output_named(&mut self, wire_id: WireId, name: &str) -> CircuitResult<()> {
+        self.mark_output(wire_id)?;
+        self.output_names.insert(name.into(), wire_id);
+        Ok(())
+    }
+
+    /// Number of input wires, which is the number of values evaluation takes.
+    pub fn input_arity(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Number of output positions, which is the number of values evaluation returns.
+    pub fn output_arity(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Check that `inputs` can be evaluated on: there is exactly one value for each input wire, in input order.
+    /// This is the check `eval_local` does before evaluating, without evaluating anything.
+    /// See `check_assignment` for checking values of all the wires instead.
+    ///
+    /// Returns `CircuitError::InputLengthMismatch` otherwise.
+    pub fn check_inputs<T: Ring>(&self, inputs: &[T]) -> CircuitResult<()> {
+        if inputs.len() != self.input_arity() {
+            return Err(CircuitError::InputLengthMismatch {
+                expected: self.input_arity(),
+                got: inputs.len(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn get_input_by_name(&self, name: &str) -> Option<WireId> {
+        self.input_names.get(name).copied()
+    }
+
+    pub fn get_output_by_name(&self, name: &str) -> Option<WireId> {
+        self.output_names.get(name).copied()
+    }
+
+    /// Returns all the named outputs, ordered by name.
+    pub fn get_output_names(&self) -> &BTreeMap<String, WireId> {
+        &self.output_names
+    }
+}
+
+/// Serialized form of a circuit.
+/// Gate count is the length of the gate list, so only the wire counter is stored.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct CircuitRef<'a> {
+    wire_offset: usize,
+    wire_count: usize,
+    inputs: &'a [WireId],
+    outputs: &'a [WireId],
+    gates: &'a [Gate],
+    input_names: &'a BTreeMap<String, WireId>,
+    output_names: &'a BTreeMap<String, WireId>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct CircuitRepr {
+    wire_offset: usize,
+    wire_count: usize,
+    inputs: Vec<WireId>,
+    outputs: Vec<WireId>,
+    gates: Vec<Gate>,
+    #[serde(default)]
+    input_names: BTreeMap<String, WireId>,
+    #[serde(default)]
+    output_names: BTreeMap<String, WireId>,
+}
+
+/// Constants are stored without their ring type, so a circuit with constants can't be serialized.
+/// This includes every circuit with a `ScalarMul` gate, since its scalar is a constant wire.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Circuit {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !self.constants.is_empty() {
+            return Err(serde::ser::Error::custom(
+                "circuit with constant wires can't be serialized",
+            ));
+        }
+
+        let repr = CircuitRef {
+            wire_offset: self.wire_offset,
+            wire_count: self.wire_count,
+            inputs: &self.inputs,
+            outputs: &self.outputs,
+            gates: &self.gates,
+            input_names: &self.input_names,
+            output_names: &self.output_names,
+        };
+        serde::Serialize::serialize(&repr, serializer)
+    }
+}
+
+/// Rejects gates whose id is not their position in the gate list and wire ids outside of the wire counter,
+/// so that circuits built afterwards keep getting fresh ids.
+/// Also rejects `ScalarMul` gates: constant wires are not serialized, so their scalar can't be a constant.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Circuit {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let repr: CircuitRepr = serde::Deserialize::deserialize(deserializer)?;
+        if repr.wire_count < repr.wire_offset {
+            return Err(D::Error::custom("wire count is smaller than wire offset"));
+        }
+
+        for (i, gate) in repr.gates.iter().enumerate() {
+            let id = gate.id();
+            if id != i {
+                return Err(D::Error::custom(format_args!(
+                    "gate at position {} has id {}",
+                    i, id
+                )));
+            }
+        }
+
+        if let Some(gate) = repr
+            .gates
+            .iter()
+            .find(|gate| gate.gate_type() == GateType::ScalarMul)
+        {
+            return Err(D::Error::custom(format_args!(
+                "ScalarMul gate with id {} has no constant scalar wire",
+                gate.id()
+            )));
+        }
+
+        let gate_wires = repr.gates.iter().flat_map(|gate| {
+            let mut wires = gate.get_inputs();
+            wires.push(gate.get_output());
+            wires
+        });
+        let wires = repr.inputs.iter().chain(repr.outputs.iter()).copied();
+        let named_wires = repr.input_names.values().chain(repr.output_names.values());
+        let wires = wires.chain(named_wires.copied());
+        if let Some(wire) = wires
+            .chain(gate_wires)
+            .find(|wire| wire.0 < repr.wire_offset || wire.0 >= repr.wire_count)
+        {
+            return Err(D::Error::custom(format_args!(
+                "wire id {} is out of range",
+                wire.0
+            )));
+        }
+
+        Ok(Circuit {
+            inputs: repr.inputs,
+            constants: vec![],
+            outputs: repr.outputs,
+            gate_count: repr.gates.len(),
+            gates: repr.gates,
+            wire_count: repr.wire_count,
+            wire_offset: repr.wire_offset,
+            input_names: repr.input_names,
+            output_names: repr.output_names,
+            cycle_guard: false,
+            max_wires: None,
+            max_gates: None,
+            layer_cache: RefCell::new(None),
+            #[cfg(test)]
+            layer_computations: Cell::new(0),
+        })
     }
 }
 
@@ -203,24 +1143,453 @@ impl Circuit {
 mod tests {
     use super::*;
     use crate::error::CircuitError;
+    use crate::eval_local::{eval_local, EvalLocalError};
+    use crate::test_utils::Fp;
 
     #[test]
     fn simple_valid_circuit() {
         let mut circuit = Circuit::new();
         let input = circuit.create_new_wire();
-        circuit.mark_input(input);
+        circuit.mark_input(input).unwrap();
 
         let output = circuit.create_new_wire();
-        circuit.mark_output(output);
+        circuit.mark_output(output).unwrap();
+
+        assert!(circuit.is_valid().is_ok(), "Circuit should be valid");
+    }
+
+    #[test]
+    fn circuit_with_orphaned_wire_should_be_invalid() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let orphan = circuit.create_new_wire();
+        circuit.add_output_gate(GateType::Add, x, y);
+
+        assert_eq!(
+            circuit.is_valid(),
+            Err(CircuitError::DanglingWire { wire_id: orphan.0 }),
+            "Result should be CircuitError::DanglingWire"
+        );
+    }
+
+    #[test]
+    fn circuit_with_unconsumed_gate_should_be_invalid() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, x, y);
+
+        // neither read by any gate nor marked as output
+        let unused = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, x, y, unused);
+
+        assert_eq!(
+            circuit.is_valid(),
+            Err(CircuitError::UnreachableWire { wire_id: unused.0 }),
+            "Result should be CircuitError::UnreachableWire"
+        );
+    }
+
+    #[test]
+    fn add_output_gate_marks_output() {
+        let mut circuit = Circuit::new();
+        let x = circuit.create_new_wire();
+        let y = circuit.create_new_wire();
+        circuit.mark_input(x).unwrap();
+        circuit.mark_input(y).unwrap();
 
+        let out = circuit.add_output_gate(GateType::Mul, x, y);
+
+        assert_eq!(circuit.get_all_outputs(), &[out]);
+        assert_eq!(circuit.get_wire_count(), 3);
+        assert_eq!(circuit.get_gate(0).unwrap().get_output(), out);
         assert!(circuit.is_valid().is_ok(), "Circuit should be valid");
     }
 
+    #[test]
+    fn add_gate_checked_rejects_self_loop() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let w = circuit.create_new_wire();
+
+        assert_eq!(
+            circuit.add_gate_checked(GateType::Add, w, x, w),
+            Err(CircuitError::SelfLoop { gate_id: 0 })
+        );
+        assert_eq!(
+            circuit.get_gate_count(),
+            0,
+            "Rejected gate should not be added"
+        );
+        assert_eq!(circuit.add_gate_checked(GateType::Add, x, x, w), Ok(0));
+    }
+
+    #[test]
+    fn checked_gates_reject_neg() {
+        let mut circuit = Circuit::with_cycle_guard();
+        let x = circuit.add_input();
+        let w = circuit.create_new_wire();
+
+        let error = CircuitError::ArityMismatch {
+            gate_type: GateType::Neg,
+            got: 2,
+        };
+        assert_eq!(circuit.add_gate_checked(GateType::Neg, x, x, w), Err(error));
+        assert_eq!(
+            circuit.try_add_gate(GateType::Neg, x, x, w),
+            Err(CircuitError::ArityMismatch {
+                gate_type: GateType::Neg,
+                got: 2
+            })
+        );
+        assert_eq!(circuit.get_gate_count(), 0);
+    }
+
+    #[test]
+    fn compression_tree_over_leaves() {
+        let add = |circuit: &mut Circuit, x, y| {
+            let out = circuit.create_new_wire();
+            circuit.add_gate(GateType::Add, x, y, out);
+            out
+        };
+
+        let mut circuit = Circuit::new();
+        let leaves: Vec<WireId> = (0..4).map(|_| circuit.add_input()).collect();
+        let root = circuit.compression_tree(&leaves, add);
+        circuit.mark_output(root).unwrap();
+
+        assert_eq!(circuit.get_gate_count(), 3);
+        let inputs = [1, 2, 3, 4].map(Fp::from).to_vec();
+        assert_eq!(eval_local(&circuit, inputs), Ok(vec![Fp::from(10)]));
+
+        // the last leaf is carried up
+        let mut circuit = Circuit::new();
+        let leaves: Vec<WireId> = (0..3).map(|_| circuit.add_input()).collect();
+        circuit.compression_tree(&leaves, add);
+        assert_eq!(circuit.get_gate_count(), 2);
+    }
+
+    #[test]
+    fn remove_middle_gate() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // a = x + y
+        // b = a * x
+        // out = b + y
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, a);
+        let middle = circuit.add_gate(GateType::Mul, a, x, b);
+        let out = circuit.add_output_gate(GateType::Add, b, y);
+
+        assert_eq!(circuit.remove_gate(middle), Ok(()));
+        assert_eq!(circuit.get_gate_count(), 2);
+
+        // the last gate takes the id of the removed one
+        assert_eq!(circuit.get_gate(1).unwrap().get_output(), out);
+        assert!(circuit.get_gate(2).is_none());
+        assert_eq!(crate::detect_cycle::detect_cycle(&circuit), None);
+
+        // b is left without a value until it is driven again
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(2), Fp::from(3)]),
+            Err(EvalLocalError::EmptyWire {
+                wire_ids: vec![b.0, out.0]
+            })
+        );
+
+        // b = a - x
+        circuit.add_gate(GateType::Sub, a, x, b);
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(2), Fp::from(3)]),
+            Ok(vec![Fp::from(6)])
+        );
+
+        assert_eq!(
+            circuit.remove_gate(3),
+            Err(CircuitError::UnknownGate { gate_id: 3 })
+        );
+    }
+
+    #[test]
+    fn wire_ids_as_keys() {
+        let mut circuit = Circuit::new();
+        let wires: Vec<WireId> = (0..4).map(|_| circuit.create_new_wire()).collect();
+
+        let set: std::collections::HashSet<WireId> = wires[..3].iter().copied().collect();
+        assert!(set.contains(&wires[0]));
+        assert!(set.contains(&wires[2]));
+        assert!(!set.contains(&wires[3]));
+
+        let ordered: BTreeMap<WireId, usize> = wires
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, w)| (*w, i))
+            .collect();
+        assert_eq!(ordered.keys().copied().collect::<Vec<_>>(), wires);
+        assert!(wires[0] < wires[1]);
+    }
+
+    #[test]
+    fn gate_id_accessor() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, x, y);
+        let neg = circuit.add_neg(x);
+        circuit.mark_output(neg).unwrap();
+
+        let first = circuit.get_gate(0).unwrap();
+        assert_eq!(first.id(), 0);
+        assert_eq!(circuit.get_gate(1).unwrap().id(), 1);
+        assert_eq!(GateId::from(first), 0);
+        assert_eq!(GateType::from(first), GateType::Add);
+    }
+
+    #[test]
+    fn mark_unknown_wire() {
+        let mut circuit = Circuit::new();
+        for _ in 0..3 {
+            circuit.create_new_wire();
+        }
+
+        assert_eq!(
+            circuit.mark_input(WireId(999)),
+            Err(CircuitError::UnknownWire { wire_id: 999 })
+        );
+        assert_eq!(
+            circuit.mark_output(WireId(3)),
+            Err(CircuitError::UnknownWire { wire_id: 3 })
+        );
+        assert_eq!(circuit.input_arity(), 0);
+        assert_eq!(circuit.output_arity(), 0);
+
+        // ids below the wire offset are not wires of the circuit either
+        let mut circuit = Circuit::with_wire_offset(10);
+        circuit.create_new_wire();
+        assert_eq!(
+            circuit.mark_input(WireId(0)),
+            Err(CircuitError::UnknownWire { wire_id: 0 })
+        );
+    }
+
+    #[test]
+    fn mark_input_twice() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.create_new_wire();
+
+        assert_eq!(
+            circuit.mark_input(x),
+            Err(CircuitError::DuplicateInput { wire_id: x.0 })
+        );
+        assert_eq!(circuit.mark_input(y), Ok(()));
+        assert_eq!(circuit.input_arity(), 2);
+
+        // an output may be marked more than once
+        let out = circuit.add_output_gate(GateType::Add, x, y);
+        assert_eq!(circuit.mark_output(out), Ok(()));
+        assert_eq!(circuit.output_arity(), 2);
+    }
+
+    #[test]
+    fn from_parts_matches_builder() {
+        use crate::builder::CircuitBuilder;
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let circuit = Circuit::from_parts(
+            5,
+            vec![
+                (GateType::Add, WireId(0), WireId(1), WireId(3)),
+                (GateType::Mul, WireId(2), WireId(3), WireId(4)),
+            ],
+            vec![WireId(0), WireId(1), WireId(2)],
+            vec![WireId(3), WireId(4)],
+        )
+        .unwrap();
+        assert_eq!(circuit.get_gate(1).unwrap().id(), 1);
+
+        let b = CircuitBuilder::new();
+        let in1 = b.input();
+        let in2 = b.input();
+        let in3 = b.input();
+        let out1 = b.add(in1, in2);
+        let out2 = b.mul(in3, out1);
+        b.output(out1).unwrap();
+        b.output(out2).unwrap();
+        let built = b.build().unwrap();
+
+        let inputs: Vec<Fp> = vec![1.into(), 2.into(), 3.into()];
+        assert_eq!(
+            eval_local(&circuit, inputs.clone()),
+            eval_local(&built, inputs)
+        );
+    }
+
+    #[test]
+    fn from_parts_rejects_unknown_wire() {
+        let result = Circuit::from_parts(
+            3,
+            vec![(GateType::Add, WireId(0), WireId(1), WireId(3))],
+            vec![WireId(0), WireId(1)],
+            vec![WireId(3)],
+        );
+        assert_eq!(result.err(), Some(CircuitError::UnknownWire { wire_id: 3 }));
+    }
+
+    #[test]
+    fn check_inputs_length() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Mul, x, y);
+
+        assert_eq!(circuit.check_inputs(&[Fp::from(1), Fp::from(2)]), Ok(()));
+        assert_eq!(
+            circuit.check_inputs(&[Fp::from(1)]),
+            Err(CircuitError::InputLengthMismatch {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert_eq!(
+            circuit.check_inputs::<Fp>(&[Fp::from(1); 3]),
+            Err(CircuitError::InputLengthMismatch {
+                expected: 2,
+                got: 3
+            })
+        );
+    }
+
+    #[test]
+    fn gate_stream_yields_gates_in_order() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let a = circuit.add_output_gate(GateType::Add, x, y);
+        let b = circuit.add_output_gate(GateType::Mul, a, y);
+
+        let streamed: Vec<(GateType, WireId)> = circuit
+            .gate_stream()
+            .map(|gate| (gate.gate_type(), gate.get_output()))
+            .collect();
+        assert_eq!(streamed, vec![(GateType::Add, a), (GateType::Mul, b)]);
+    }
+
+    #[test]
+    fn capacity_limits_are_enforced() {
+        let mut circuit = Circuit::with_capacity_limits(2, 1);
+        let x = circuit.try_create_new_wire().unwrap();
+        let y = circuit.try_create_new_wire().unwrap();
+        assert_eq!(
+            circuit.try_create_new_wire(),
+            Err(CircuitError::CapacityExceeded {
+                capacity: Capacity::Wires,
+                limit: 2
+            })
+        );
+        assert_eq!(circuit.get_wire_count(), 2);
+
+        assert_eq!(circuit.try_add_gate(GateType::Add, x, x, y), Ok(0));
+        assert_eq!(
+            circuit.try_add_gate(GateType::Mul, x, y, y),
+            Err(CircuitError::CapacityExceeded {
+                capacity: Capacity::Gates,
+                limit: 1
+            })
+        );
+        assert_eq!(circuit.get_gate_count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than 2 wires")]
+    fn create_new_wire_panics_past_the_limit() {
+        let mut circuit = Circuit::with_capacity_limits(2, 1);
+        circuit.add_input();
+        circuit.add_input();
+        circuit.add_input();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn wire_ids_start_at_offset() {
+        let mut circuit = Circuit::with_wire_offset(100);
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let out = circuit.add_output_gate(GateType::Mul, x, y);
+
+        assert_eq!((x, y, out), (WireId(100), WireId(101), WireId(102)));
+        assert_eq!(circuit.get_wire_offset(), 100);
+        assert_eq!(circuit.get_wire_count(), 103);
+        assert!(
+            circuit.lint().is_empty(),
+            "Reserved ids should not be reported"
+        );
+
+        let inputs = vec![Fp::from(3), Fp::from(5)];
+        assert_eq!(eval_local(&circuit, inputs.clone()), Ok(vec![Fp::from(15)]));
+        assert_eq!(circuit.witness(inputs).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn clone_remapped_shifts_wires() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let out = circuit.add_output_gate(GateType::Add, x, y);
+        circuit.mark_output_named(out, "sum").unwrap();
+
+        let copy = circuit.clone_remapped(10);
+        assert_eq!(copy.get_all_inputs(), &[WireId(10), WireId(11)]);
+        assert_eq!(copy.get_all_outputs(), &[WireId(12), WireId(12)]);
+        assert_eq!(copy.get_output_by_name("sum"), Some(WireId(12)));
+        assert_eq!(copy.get_wire_offset(), 10);
+        assert_eq!(copy.get_wire_count(), 13);
+        assert_eq!(
+            copy.get_gate(0).unwrap().get_inputs(),
+            vec![WireId(10), WireId(11)]
+        );
+        assert_eq!(copy.is_valid(), Ok(()));
+        assert_eq!(
+            eval_local(&copy, vec![Fp::from(2), Fp::from(3)]),
+            Ok(vec![Fp::from(5), Fp::from(5)])
+        );
+    }
+
+    #[test]
+    fn wire_connections_of_multiple_outputs() {
+        let mut circuit = Circuit::new();
+
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let in3 = circuit.add_input();
+        let out1 = circuit.add_output_gate(GateType::Add, in1, in2);
+        let out2 = circuit.add_output_gate(GateType::Mul, in3, out1);
+
+        let connections = circuit.wire_connections();
+        let connection = |to_ids: Vec<usize>, from_id| WireConnection { to_ids, from_id };
+        assert_eq!(connections.len(), 5);
+        assert_eq!(connections[in1.0], connection(vec![0], None));
+        assert_eq!(connections[in2.0], connection(vec![0], None));
+        assert_eq!(connections[in3.0], connection(vec![1], None));
+        assert_eq!(connections[out1.0], connection(vec![1], Some(0)));
+        assert_eq!(connections[out2.0], connection(vec![], Some(1)));
+    }
+
     #[test]
     fn circuit_without_input_should_be_invalid() {
         let mut circuit = Circuit::new();
         let output = circuit.create_new_wire();
-        circuit.mark_output(output);
+        circuit.mark_output(output).unwrap();
         let res = circuit.is_valid();
         assert!(res.is_err(), "Circuit should be invalid");
         assert_eq!(
@@ -230,11 +1599,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn wire_below_offset_should_be_invalid() {
+        let mut circuit = Circuit::with_wire_offset(10);
+        let input = circuit.add_input();
+        let out = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, input, WireId(0), out);
+        circuit.mark_output(out).unwrap();
+
+        assert_eq!(
+            circuit.is_valid(),
+            Err(CircuitError::UnknownWire { wire_id: 0 })
+        );
+    }
+
     #[test]
     fn circuit_without_output_should_be_invalid() {
         let mut circuit = Circuit::new();
         let input = circuit.create_new_wire();
-        circuit.mark_input(input);
+        circuit.mark_input(input).unwrap();
 
         let res = circuit.is_valid();
         assert!(res.is_err(), "Circuit should be invalid");
@@ -244,4 +1627,74 @@ mod tests {
             "Result should be CircuitError::EmptyOutput"
         );
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        // declared out of wire id order, to check the order is kept
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_output(out2).unwrap();
+        circuit.mark_output(out1).unwrap();
+
+        let json = serde_json::to_string(&circuit).unwrap();
+        let mut restored: Circuit = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.is_valid(), Ok(()));
+        assert_eq!(restored.get_all_inputs(), circuit.get_all_inputs());
+        assert_eq!(restored.get_all_outputs(), circuit.get_all_outputs());
+        assert_eq!(restored.get_gate_count(), circuit.get_gate_count());
+        assert_eq!(restored.get_wire_count(), circuit.get_wire_count());
+        for (before, after) in circuit.gate_stream().zip(restored.gate_stream()) {
+            assert_eq!(before.gate_type(), after.gate_type());
+            assert_eq!(before.get_inputs(), after.get_inputs());
+            assert_eq!(before.get_output(), after.get_output());
+        }
+
+        // counters are restored, so new gates and wires get fresh ids
+        let wire = restored.create_new_wire();
+        assert_eq!(wire, WireId(circuit.get_wire_count()));
+        let gate = restored.add_gate(GateType::Add, out1, out2, wire);
+        assert_eq!(gate, circuit.get_gate_count());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_constants() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let one = circuit.create_constant_wire(Fp::from(1));
+        circuit.add_output_gate(GateType::Add, x, one);
+
+        assert!(serde_json::to_string(&circuit).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_scalar_mul_without_constant() {
+        let json = r#"{
+            "wire_offset": 0,
+            "wire_count": 3,
+            "inputs": [0, 1],
+            "outputs": [2],
+            "gates": [{"ScalarMul": {"id": 0, "x": 0, "scalar": 1, "out": 2}}]
+        }"#;
+
+        assert!(serde_json::from_str::<Circuit>(json).is_err());
+    }
 }
@@ -0,0 +1,97 @@
+//! Composition of circuits, so that gadgets built once can be reused as parts of larger circuits.
+
+use crate::circuit::{Circuit, WireId};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl Circuit {
+    /// Copy all the gates and constant wires of `other` into this circuit.
+    /// Each pair `(other_input, wire)` of `input_mapping` connects an input wire of `other` to a wire of this circuit.
+    /// Every other wire of `other` becomes a new wire of this circuit, created in id order
+    /// from the current wire count. Gates get new ids from the current gate count, in their original order.
+    /// Inputs of `other` which are not in `input_mapping` become new inputs of this circuit.
+    ///
+    /// Returns the wires of this circuit corresponding to the outputs of `other`, in order.
+    /// They are not marked as outputs, so they can be read by further gates.
+    ///
+    /// Panics if a wire in `input_mapping` is not an input of `other`.
+    pub fn merge(&mut self, other: &Circuit, input_mapping: &[(WireId, WireId)]) -> Vec<WireId> {
+        let mut wires: Vec<Option<WireId>> = vec![None; other.get_wire_count()];
+        for (other_input, wire) in input_mapping.iter() {
+            assert!(
+                other.get_all_inputs().contains(other_input),
+                "wire with id{} is not an input of the merged circuit",
+                other_input.0
+            );
+            wires[other_input.0] = Some(*wire);
+        }
+        for input in other.get_all_inputs() {
+            if wires[input.0].is_none() {
+                wires[input.0] = Some(self.add_input());
+            }
+        }
+        for wire in wires.iter_mut().skip(other.get_wire_offset()) {
+            if wire.is_none() {
+                *wire = Some(self.create_new_wire());
+            }
+        }
+        let map = |wire: WireId| wires[wire.0].expect("wire of the merged circuit");
+
+        for constant in other.get_constants() {
+            self.push_constant(constant.clone_to(map(constant.get_wire_id())));
+        }
+        for gate in other.gate_stream() {
+            let gate = gate.remapped(self.get_gate_count(), map);
+            self.push_gate(gate);
+        }
+
+        other
+            .get_all_outputs()
+            .iter()
+            .map(|out| map(*out))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+    use crate::detect_cycle::detect_cycle;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn merge_adder_gadget() {
+        // gadget
+        // sum = x + y + 1
+        let mut adder = Circuit::new();
+        let x = adder.add_input();
+        let y = adder.add_input();
+        let one = adder.create_constant_wire(Fp::from(1));
+        let sum = adder.create_new_wire();
+        adder.add_gate(GateType::Add, x, y, sum);
+        adder.add_output_gate(GateType::Add, sum, one);
+
+        // Circuit
+        // out = (a * b + c + 1) * a
+        let mut circuit = Circuit::new();
+        let a = circuit.add_input();
+        let b = circuit.add_input();
+        let c = circuit.add_input();
+        let product = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, a, b, product);
+
+        let outputs = circuit.merge(&adder, &[(x, product), (y, c)]);
+        assert_eq!(outputs.len(), 1);
+        circuit.add_output_gate(GateType::Mul, outputs[0], a);
+
+        assert_eq!(circuit.get_gate_count(), 4);
+        assert_eq!(circuit.get_all_inputs(), &[a, b, c]);
+        assert_eq!(circuit.is_valid(), Ok(()));
+        assert_eq!(detect_cycle(&circuit), None);
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(2), Fp::from(3), Fp::from(4)]),
+            Ok(vec![Fp::from(22)])
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Content hashes of gates, as keys for caching garbled tables across circuits.
+//!
+//! The hash of a gate covers its type and the hashes of its operands, Merkle-style,
+//! so two gates get the same hash exactly when the subcircuits below them have the same shape.
+//! Wire and gate ids don't affect the hash. Input wires, and other operands which are not produced by a gate,
+//! all hash to the same leaf: the hash describes how a value is computed, not which inputs it is computed from.
+//! Constant wires are part of the computation, so each of them hashes to a leaf covering its value.
+
+use crate::circuit::{Circuit, GateId, GateType};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Domain separation tags, so that leaves, constants and gates can never collide.
+const LEAF_TAG: u8 = 0;
+const GATE_TAG: u8 = 1;
+const CONSTANT_TAG: u8 = 2;
+
+impl Circuit {
+    /// Returns the content hash of every gate.
+    /// Gates are only hashed if they can be ordered, see `gate_layers`.
+    /// If the circuit has a cycle or an undriven wire, the map is empty.
+    pub fn gate_content_hashes(&self) -> HashMap<GateId, [u8; 32]> {
+        let mut hashes = HashMap::new();
+        let layers = match self.gate_layers() {
+            Ok(layers) => layers,
+            Err(_) => return hashes,
+        };
+
+        let gates = self.get_all_gates();
+        let connections = self.wire_connections();
+        let mut leaf_hasher = Sha256::new();
+        leaf_hasher.update([LEAF_TAG]);
+        let leaf: [u8; 32] = leaf_hasher.finalize().into();
+        let constant_leaves: HashMap<usize, [u8; 32]> = self
+            .get_constants()
+            .iter()
+            .map(|constant| {
+                let mut hasher = Sha256::new();
+                hasher.update([CONSTANT_TAG]);
+                hasher.update(constant.describe_value().as_bytes());
+                (constant.get_wire_id().0, hasher.finalize().into())
+            })
+            .collect();
+
+        for id in layers.into_iter().flatten() {
+            let gate = &gates[id];
+            let mut hasher = Sha256::new();
+            hasher.update([GATE_TAG]);
+            hasher.update([match gate.gate_type() {
+                GateType::Add => 0,
+                GateType::Mul => 1,
+                GateType::Sub => 2,
+                GateType::Neg => 3,
+                GateType::ScalarMul => 4,
+            }]);
+            for operand in gate.get_inputs() {
+                // producers are hashed first, since layers are in topological order
+                let operand_hash = match connections[operand.0].from_id {
+                    Some(p) => hashes[&p],
+                    None => *constant_leaves.get(&operand.0).unwrap_or(&leaf),
+                };
+                hasher.update(operand_hash);
+            }
+            hashes.insert(id, hasher.finalize().into());
+        }
+
+        hashes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn identical_subtrees_share_hashes() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = (in1 + in2) * in3 (gate0, gate1)
+        // out2 = (in4 + in5) * in6 (gate2, gate3)
+        // out3 = (in1 * in2) * in3 (gate4, gate5)
+        let inputs: Vec<WireId> = (0..6).map(|_| circuit.add_input()).collect();
+        for (gate_type, x, y, z) in [
+            (GateType::Add, 0, 1, 2),
+            (GateType::Add, 3, 4, 5),
+            (GateType::Mul, 0, 1, 2),
+        ] {
+            let inner = circuit.create_new_wire();
+            circuit.add_gate(gate_type, inputs[x], inputs[y], inner);
+            circuit.add_output_gate(GateType::Mul, inner, inputs[z]);
+        }
+
+        let hashes = circuit.gate_content_hashes();
+        assert_eq!(hashes.len(), 6);
+        assert_eq!(hashes[&0], hashes[&2], "Inner adds should share a hash");
+        assert_eq!(
+            hashes[&1], hashes[&3],
+            "Roots of the same shape should share a hash"
+        );
+        assert_ne!(
+            hashes[&0], hashes[&4],
+            "Add and Mul should hash differently"
+        );
+        assert_ne!(
+            hashes[&1], hashes[&5],
+            "Different subtrees should hash differently"
+        );
+    }
+
+    #[test]
+    fn constants_are_hashed_by_value() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 * 1 (gate0)
+        // out2 = in1 * 0 (gate1)
+        // out3 = in1 * 2 (gate2)
+        // out4 = in1 * in2 (gate3)
+        // out5 = in1 * 2 (gate4)
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let one = circuit.create_constant_wire(Fp::from(1));
+        let zero = circuit.create_constant_wire(Fp::from(0));
+        let two = circuit.create_constant_wire(Fp::from(2));
+        let other_two = circuit.create_constant_wire(Fp::from(2));
+        for (gate_type, y) in [
+            (GateType::ScalarMul, one),
+            (GateType::ScalarMul, zero),
+            (GateType::Mul, two),
+            (GateType::Mul, in2),
+            (GateType::Mul, other_two),
+        ] {
+            circuit.add_output_gate(gate_type, in1, y);
+        }
+
+        let hashes = circuit.gate_content_hashes();
+        assert_eq!(hashes.len(), 5);
+        assert_ne!(
+            hashes[&0], hashes[&1],
+            "Different scalars should hash differently"
+        );
+        assert_ne!(
+            hashes[&2], hashes[&3],
+            "A constant should not hash like an input"
+        );
+        assert_eq!(
+            hashes[&2], hashes[&4],
+            "Equal constants should share a hash"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Dead-gate elimination: gates which no output of the circuit depends on are removed,
+//! so they don't have to be garbled.
+
+use crate::analysis::backward_reachable;
+use crate::circuit::Circuit;
+
+impl Circuit {
+    /// Remove every gate which is not on a path to an output wire, and return the number of removed gates.
+    /// Gates feeding output wires, directly or through other gates, are kept.
+    /// A second call removes nothing.
+    ///
+    /// Wires which no output depends on are removed too, except input wires, which are a part of the
+    /// interface of the circuit. Remaining gates and wires keep their order and are renumbered,
+    /// so ids obtained before are no longer valid. Inputs and outputs keep their positions and names.
+    pub fn eliminate_dead_gates(&mut self) -> usize {
+        let mut live = backward_reachable(self, self.get_all_outputs());
+
+        let keep: Vec<bool> = self
+            .gate_stream()
+            .map(|gate| live[gate.get_output().0])
+            .collect();
+        let removed = keep.iter().filter(|keep| !**keep).count();
+        self.retain_gates(&keep);
+
+        for input in self.get_all_inputs() {
+            live[input.0] = true;
+        }
+        self.retain_wires(&live);
+
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn stray_gate_is_removed() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // sum = x + y
+        // stray = x * y, which is not used
+        // out = sum * y
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        let stray = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, x, y, stray);
+        circuit.add_output_gate(GateType::Mul, sum, y);
+
+        assert_eq!(circuit.eliminate_dead_gates(), 1);
+        assert_eq!(circuit.get_gate_count(), 2);
+        let types: Vec<GateType> = circuit.gate_stream().map(|gate| gate.gate_type()).collect();
+        assert_eq!(types, vec![GateType::Add, GateType::Mul]);
+        // the output wire of the stray gate is removed too
+        assert_eq!(circuit.get_wire_count(), 4);
+        assert_eq!(circuit.is_valid(), Ok(()));
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(2), Fp::from(3)]),
+            Ok(vec![Fp::from(15)])
+        );
+
+        assert_eq!(circuit.eliminate_dead_gates(), 0);
+    }
+
+    #[test]
+    fn gates_feeding_outputs_transitively_are_kept() {
+        let mut circuit = Circuit::new();
+
+        // out = ((x + x) * x) - x
+        let x = circuit.add_input();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, x, a);
+        circuit.add_gate(GateType::Mul, a, x, b);
+        circuit.add_output_gate(GateType::Sub, b, x);
+        // a dead chain reading a live wire
+        let dead = circuit.add_neg(b);
+        circuit.add_neg(dead);
+
+        assert_eq!(circuit.eliminate_dead_gates(), 2);
+        assert_eq!(circuit.get_gate_count(), 3);
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(3)]),
+            Ok(vec![Fp::from(15)])
+        );
+    }
+}
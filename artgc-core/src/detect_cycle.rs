@@ -1,22 +1,73 @@
-use crate::circuit::{Circuit, Gate, WireId};
-use std::collections::HashSet;
+use crate::circuit::{check_binary, Circuit, GateType, WireId};
+use crate::error::{CircuitError, CircuitResult};
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
 
-#[derive(Clone, Debug)]
-struct WireConnection {
-    // List of id of gates which this wire goes into.
-    to_ids: Vec<usize>,
+/// Wire id of a stack frame marking that the search below its gate is done.
+const POST_VISIT: usize = usize::MAX;
 
-    // Id of a gate which this wire comes out of.
-    // Wires marked as inputs of a circuit has None.
-    from_id: Option<usize>,
-}
+impl Circuit {
+    /// Same as `add_gate`, but on a circuit created by `with_cycle_guard`, the gate is only added
+    /// if it doesn't close a cycle, so that cycles are reported by the call introducing them.
+    /// Otherwise the gate is added unchecked, so that the check can be switched on by changing the constructor only.
+    ///
+    /// A circuit built with this stays acyclic, so the new gate closes a cycle exactly if its output wire
+    /// is one of its input wires or they depend on it. Only the gates its inputs depend on are searched,
+    /// after one pass over the gates to find the gate driving each wire.
+    ///
+    /// Returns `CircuitError::ArityMismatch` if `gate_type` is `GateType::Neg`,
+    /// and `CircuitError::CapacityExceeded` if no more gates can be added, see `with_capacity_limits`.
+    /// On a guarded circuit, also returns `CircuitError::UnknownWire` if a wire has not been created,
+    /// and `CircuitError::CyclicPath` with the id the gate would have had and its input wire depending on the output wire.
+    /// The circuit is unchanged on error.
+    pub fn try_add_gate(
+        &mut self,
+        gate_type: GateType,
+        x_id: WireId,
+        y_id: WireId,
+        out_id: WireId,
+    ) -> CircuitResult<usize> {
+        check_binary(gate_type)?;
+        self.check_gate_capacity()?;
+        if self.has_cycle_guard() {
+            for wire in [x_id, y_id, out_id] {
+                self.check_wire(wire)?;
+            }
+            if let Some(wire) = self.first_dependent(&[x_id, y_id], out_id) {
+                return Err(CircuitError::CyclicPath {
+                    gate_id: self.get_gate_count(),
+                    wire_id: wire.0,
+                });
+            }
+        }
+        Ok(self.add_gate(gate_type, x_id, y_id, out_id))
+    }
 
-impl Default for WireConnection {
-    fn default() -> Self {
-        WireConnection {
-            to_ids: vec![],
-            from_id: None,
+    /// Returns the first of `wires` whose value depends on `target`, searching backwards through the gates driving them.
+    fn first_dependent(&self, wires: &[WireId], target: WireId) -> Option<WireId> {
+        let mut drivers = vec![vec![]; self.get_wire_count()];
+        for gate in self.gate_stream() {
+            drivers[gate.get_output().0].push(gate.id());
         }
+
+        wires.iter().copied().find(|wire| {
+            let mut visited = vec![false; self.get_wire_count()];
+            let mut stack = vec![*wire];
+            while let Some(wire) = stack.pop() {
+                if wire == target {
+                    return true;
+                }
+                if visited[wire.0] {
+                    continue;
+                }
+                visited[wire.0] = true;
+                for gate_id in drivers[wire.0].iter() {
+                    stack.extend(self.get_all_gates()[*gate_id].get_inputs());
+                }
+            }
+            false
+        })
     }
 }
 
@@ -24,61 +75,41 @@ impl Default for WireConnection {
 /// If it has any, returns pair of gate id and wire id of the starting node of the cycle.
 ///
 /// Do Depth First Search to detect cyclic path in a circuit
+/// The search is iterative, so it works on circuits of any depth,
+/// and expands every gate once, so it takes linear time in the size of the circuit.
 pub fn detect_cycle(circuit: &Circuit) -> Option<(usize, WireId)> {
-    // prepare DFS
-    // scan all the gates and store how gates are connected.
-    let mut wire_connections = vec![WireConnection::default(); circuit.get_wire_count()];
-
-    for gate in circuit.get_all_gates() {
-        let (id, x, y, out): (usize, usize, usize, usize) = match gate {
-            Gate::Add { id, x, y, out } => (*id, x.into(), y.into(), out.into()),
-            Gate::Mul { id, x, y, out } => (*id, x.into(), y.into(), out.into()),
-        };
-
-        wire_connections[x].to_ids.push(id);
-        wire_connections[y].to_ids.push(id);
-        wire_connections[out].from_id = Some(id)
-    }
-
-    let mut gate_visited = vec![0; circuit.get_gate_count()];
-    let gates = circuit.get_all_gates();
-
-    // Do DFS
-    fn dfs(
-        gate_id: usize,
-        wire_id: usize,
-        gates: &[Gate],
-        gate_visited: &mut Vec<usize>,
-        wire_connections: &Vec<WireConnection>,
-    ) -> Option<(usize, usize)> {
-        let gate = &gates[gate_id];
-        let (id, out): (usize, usize) = match gate {
-            Gate::Add { id, out, .. } => (*id, out.into()),
-            Gate::Mul { id, out, .. } => (*id, out.into()),
-        };
-        if gate_visited[id] != 0 {
-            // this gate has been visited at least once.
-            // which means this node is a part of a cyclic path in the circuit
-            return Some((gate_id, wire_id));
-        }
-
-        gate_visited[id] += 1;
+    // the search can't exceed a limit it doesn't have
+    detect_cycle_with_limit(circuit, None).unwrap()
+}
 
-        // get out wire of this gate
-        let wire = wire_connections.get(out).unwrap();
+/// State of a gate during the search.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    /// Not reached yet.
+    New,
+    /// On the current path: the gates after it are being searched.
+    OnPath,
+    /// All the gates after it have been searched without finding a cycle.
+    Done,
+}
 
-        for next_gate_id in wire.to_ids.iter() {
-            if let Some(pair) = dfs(*next_gate_id, out, gates, gate_visited, wire_connections) {
-                return Some(pair);
-            }
-        }
+/// Same as `detect_cycle`, but gives up once the search has expanded more than `limit` gates,
+/// returning `CircuitError::AnalysisLimitExceeded`.
+/// Every gate is expanded at most once, so a limit of at least the number of gates is never exceeded.
+/// Use this instead of `detect_cycle` to bound the work spent on untrusted circuits.
+/// `None` means no limit.
+pub fn detect_cycle_with_limit(
+    circuit: &Circuit,
+    limit: Option<usize>,
+) -> CircuitResult<Option<(usize, WireId)>> {
+    // prepare DFS
+    let wire_connections = circuit.wire_connections();
 
-        gate_visited[id] -= 1;
-        None
-    }
+    let mut gate_visits = vec![Visit::New; circuit.get_gate_count()];
+    let gates = circuit.get_all_gates();
 
     let circuit_inputs = circuit.get_all_inputs();
-    let mut input_gates = HashSet::<usize>::new();
+    let mut input_gates = BTreeSet::<usize>::new();
 
     for i in circuit_inputs.iter() {
         let id: usize = i.into();
@@ -88,19 +119,63 @@ pub fn detect_cycle(circuit: &Circuit) -> Option<(usize, WireId)> {
             }
         }
     }
-    for g in input_gates.into_iter() {
-        if let Some((gate_id, wire_id)) = dfs(g, 0, gates, &mut gate_visited, &wire_connections) {
-            return Some((gate_id, WireId::from(wire_id)));
+
+    // number of gates expanded so far
+    let mut steps = 0;
+
+    // The search starts from the gates reading an input, then from every gate no search has reached,
+    // so that cycles fed only by constant wires or by their own gates are found as well.
+    // Do DFS with an explicit stack of (gate id, id of the wire the gate was reached through) frames,
+    // so that deep circuits don't overflow the native stack.
+    // A frame with `POST_VISIT` as wire id is popped once all the gates after its gate have been searched.
+    for g in input_gates.into_iter().chain(0..circuit.get_gate_count()) {
+        if gate_visits[g] != Visit::New {
+            continue;
+        }
+        let mut stack: Vec<(usize, usize)> = vec![(g, 0)];
+
+        while let Some((gate_id, wire_id)) = stack.pop() {
+            if wire_id == POST_VISIT {
+                gate_visits[gate_id] = Visit::Done;
+                continue;
+            }
+
+            match gate_visits[gate_id] {
+                // this gate is on the current path, which means the circuit has a cyclic path through it
+                Visit::OnPath => return Ok(Some((gate_id, WireId::from(wire_id)))),
+                Visit::Done => continue,
+                Visit::New => {}
+            }
+
+            steps += 1;
+            if let Some(limit) = limit.filter(|limit| steps > *limit) {
+                return Err(CircuitError::AnalysisLimitExceeded { limit });
+            }
+
+            let gate = &gates[gate_id];
+            let (id, out) = (gate.id(), gate.get_output().0);
+            gate_visits[id] = Visit::OnPath;
+            stack.push((id, POST_VISIT));
+
+            // get out wire of this gate
+            let wire = wire_connections.get(out).unwrap();
+
+            // pushed in reverse, so that the next gates are searched in order
+            for next_gate_id in wire.to_ids.iter().rev() {
+                stack.push((*next_gate_id, out));
+            }
         }
     }
 
-    None
+    Ok(None)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::detect_cycle;
+    use super::{detect_cycle, detect_cycle_with_limit};
     use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::test_utils::Fp;
 
     #[test]
     fn circuit_with_no_cycles() {
@@ -132,10 +207,10 @@ mod tests {
         let out2 = circuit.create_new_wire();
         circuit.add_gate(GateType::Mul, in3, out1, out2);
 
-        circuit.mark_input(in1);
-        circuit.mark_input(in2);
-        circuit.mark_input(in3);
-        circuit.mark_output(out2);
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out2).unwrap();
 
         assert_eq!(detect_cycle(&circuit), None, "No cycle should be detected.");
     }
@@ -169,11 +244,58 @@ mod tests {
         let x2_id = circuit.create_new_wire();
         circuit.add_gate(GateType::Add, x2_id, out1_id, y1_id);
 
-        circuit.mark_input(x1_id);
-        circuit.mark_input(x2_id);
-        circuit.mark_output(out1_id);
+        circuit.mark_input(x1_id).unwrap();
+        circuit.mark_input(x2_id).unwrap();
+        circuit.mark_output(out1_id).unwrap();
 
         assert!(detect_cycle(&circuit).is_some(), "Cycle should be detected");
+        assert!(
+            matches!(circuit.is_valid(), Err(CircuitError::CyclicPath { .. })),
+            "Circuit with a cycle should be invalid"
+        );
+    }
+
+    #[test]
+    fn try_add_gate_rejects_closing_a_cycle() {
+        // same as `circuit_with_a_cycle`
+        let mut circuit = Circuit::with_cycle_guard();
+
+        let x1_id = circuit.create_new_wire();
+        let y1_id = circuit.create_new_wire();
+        let out1_id = circuit.create_new_wire();
+        assert_eq!(
+            circuit.try_add_gate(GateType::Mul, x1_id, y1_id, out1_id),
+            Ok(0)
+        );
+
+        let x2_id = circuit.create_new_wire();
+        assert_eq!(
+            circuit.try_add_gate(GateType::Add, x2_id, out1_id, y1_id),
+            Err(CircuitError::CyclicPath {
+                gate_id: 1,
+                wire_id: out1_id.0
+            })
+        );
+        assert_eq!(circuit.get_gate_count(), 1);
+
+        // the same gate writing to a fresh wire is fine
+        let out2_id = circuit.create_new_wire();
+        assert_eq!(
+            circuit.try_add_gate(GateType::Add, x2_id, out1_id, out2_id),
+            Ok(1)
+        );
+        assert_eq!(
+            circuit.try_add_gate(GateType::Add, out2_id, x1_id, out2_id),
+            Err(CircuitError::CyclicPath {
+                gate_id: 2,
+                wire_id: out2_id.0
+            })
+        );
+        assert_eq!(
+            circuit.try_add_gate(GateType::Add, x1_id, WireId(99), out2_id),
+            Err(CircuitError::UnknownWire { wire_id: 99 })
+        );
+        assert_eq!(detect_cycle(&circuit), None);
     }
 
     #[test]
@@ -208,12 +330,91 @@ mod tests {
         let _gate1 = circuit.add_gate(GateType::Add, in1, mid0, out1);
         let _gate2 = circuit.add_gate(GateType::Add, in2, out1, mid0);
 
-        circuit.mark_input(in0);
-        circuit.mark_input(in1);
-        circuit.mark_input(in2);
-        circuit.mark_output(out0);
-        circuit.mark_output(out1);
+        circuit.mark_input(in0).unwrap();
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_output(out0).unwrap();
+        circuit.mark_output(out1).unwrap();
 
         assert!(detect_cycle(&circuit).is_some(), "Cycle should be detected");
     }
+
+    #[test]
+    fn cycle_fed_by_constants() {
+        // a = k + b
+        // b = k * a
+        // out = in + b
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input();
+        let k = circuit.create_constant_wire(Fp::from(2));
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        let out = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, k, b, a);
+        circuit.add_gate(GateType::Mul, k, a, b);
+        circuit.add_gate(GateType::Add, input, b, out);
+        circuit.mark_output(out).unwrap();
+
+        assert!(detect_cycle(&circuit).is_some(), "Cycle should be detected");
+        assert!(matches!(
+            circuit.is_valid(),
+            Err(CircuitError::CyclicPath { .. })
+        ));
+    }
+
+    #[test]
+    fn ladder_is_validated_in_linear_time() {
+        // every level has 2 gates reading both wires of the previous level,
+        // so there are 2^200 paths through the circuit, but only 400 gates.
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input();
+        let (mut a, mut b) = (input, input);
+        for _ in 0..200 {
+            let (next_a, next_b) = (circuit.create_new_wire(), circuit.create_new_wire());
+            circuit.add_gate(GateType::Add, a, b, next_a);
+            circuit.add_gate(GateType::Mul, a, b, next_b);
+            (a, b) = (next_a, next_b);
+        }
+        circuit.mark_output(a).unwrap();
+        circuit.mark_output(b).unwrap();
+
+        assert_eq!(detect_cycle_with_limit(&circuit, Some(400)), Ok(None));
+        assert_eq!(circuit.is_valid(), Ok(()));
+    }
+
+    #[test]
+    fn detect_cycle_respects_limit() {
+        let mut circuit = Circuit::new();
+
+        // chain of 64 gates, each reading the input, so that every gate is expanded once
+        let input = circuit.add_input();
+        let mut acc = input;
+        for _ in 0..64 {
+            let next = circuit.create_new_wire();
+            circuit.add_gate(GateType::Add, acc, input, next);
+            acc = next;
+        }
+        circuit.mark_output(acc).unwrap();
+
+        assert_eq!(
+            detect_cycle_with_limit(&circuit, Some(63)),
+            Err(CircuitError::AnalysisLimitExceeded { limit: 63 })
+        );
+        assert_eq!(detect_cycle_with_limit(&circuit, Some(64)), Ok(None));
+    }
+
+    #[test]
+    fn deep_circuit_does_not_overflow() {
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input();
+        let mut acc = input;
+        for _ in 0..50_000 {
+            let next = circuit.create_new_wire();
+            circuit.add_gate(GateType::Add, acc, input, next);
+            acc = next;
+        }
+        circuit.mark_output(acc).unwrap();
+
+        assert_eq!(detect_cycle(&circuit), None, "No cycle should be detected.");
+    }
 }
@@ -0,0 +1,179 @@
+//! Structural comparison of two versions of a circuit, e.g. to review what a change of a generator did.
+
+use crate::circuit::{Circuit, Gate, GateType, WireId};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+
+/// Gate without its id. Gates with the same signature compute the same wire in the same way.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GateSignature {
+    pub gate_type: GateType,
+    pub inputs: Vec<WireId>,
+    pub output: WireId,
+}
+
+impl From<&Gate> for GateSignature {
+    fn from(gate: &Gate) -> Self {
+        GateSignature {
+            gate_type: gate.gate_type(),
+            inputs: gate.get_inputs(),
+            output: gate.get_output(),
+        }
+    }
+}
+
+/// Differences between an old and a new circuit, see `circuit_diff`.
+/// Gates are in gate id order and wires in input or output order of the circuit they come from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CircuitDiff {
+    /// Gates of the new circuit which the old one doesn't have.
+    pub added: Vec<GateSignature>,
+    /// Gates of the old circuit which the new one doesn't have.
+    pub removed: Vec<GateSignature>,
+    pub added_inputs: Vec<WireId>,
+    pub removed_inputs: Vec<WireId>,
+    pub added_outputs: Vec<WireId>,
+    pub removed_outputs: Vec<WireId>,
+}
+
+impl CircuitDiff {
+    /// Whether both circuits have the same gates, inputs and outputs, up to gate ids and ordering.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.added_inputs.is_empty()
+            && self.removed_inputs.is_empty()
+            && self.added_outputs.is_empty()
+            && self.removed_outputs.is_empty()
+    }
+}
+
+/// Compare the gates, inputs and outputs of `old` and `new`.
+/// Gates are matched by their `GateSignature` rather than by id, so inserting a gate doesn't make
+/// every later gate look changed. A gate or wire present several times is matched as many times.
+///
+/// Wire ids are compared as they are, so circuits whose wires were renumbered differ everywhere.
+pub fn circuit_diff(old: &Circuit, new: &Circuit) -> CircuitDiff {
+    let old_gates: Vec<GateSignature> = old.gate_stream().map(GateSignature::from).collect();
+    let new_gates: Vec<GateSignature> = new.gate_stream().map(GateSignature::from).collect();
+
+    CircuitDiff {
+        added: unmatched(&new_gates, &old_gates),
+        removed: unmatched(&old_gates, &new_gates),
+        added_inputs: unmatched(new.get_all_inputs(), old.get_all_inputs()),
+        removed_inputs: unmatched(old.get_all_inputs(), new.get_all_inputs()),
+        added_outputs: unmatched(new.get_all_outputs(), old.get_all_outputs()),
+        removed_outputs: unmatched(old.get_all_outputs(), new.get_all_outputs()),
+    }
+}
+
+/// Returns the items of `items` left over after matching each item of `others` with one equal item.
+fn unmatched<T: Clone + Eq + Hash>(items: &[T], others: &[T]) -> Vec<T> {
+    let mut counts: HashMap<&T, usize> = HashMap::new();
+    for other in others {
+        *counts.entry(other).or_default() += 1;
+    }
+    items
+        .iter()
+        .filter(|item| match counts.get_mut(item) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// One line per difference, prefixed with `+` for additions and `-` for removals.
+impl Display for CircuitDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (sign, gates) in [("-", &self.removed), ("+", &self.added)] {
+            for gate in gates.iter() {
+                let inputs: Vec<String> = gate.inputs.iter().map(|w| format!("w{}", w.0)).collect();
+                writeln!(
+                    f,
+                    "{} gate {:?}({}) -> w{}",
+                    sign,
+                    gate.gate_type,
+                    inputs.join(", "),
+                    gate.output.0
+                )?;
+            }
+        }
+        let wires = [
+            ("-", "input", &self.removed_inputs),
+            ("+", "input", &self.added_inputs),
+            ("-", "output", &self.removed_outputs),
+            ("+", "output", &self.added_outputs),
+        ];
+        for (sign, kind, wires) in wires {
+            for wire in wires.iter() {
+                writeln!(f, "{} {} w{}", sign, kind, wire.0)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{circuit_diff, GateSignature};
+    use crate::circuit::*;
+
+    // out = (x + y) * x
+    fn two_gates() -> (Circuit, WireId, WireId) {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        let out = circuit.add_output_gate(GateType::Mul, sum, x);
+        (circuit, y, out)
+    }
+
+    #[test]
+    fn extra_mul_gate_is_added() {
+        let (old, _, _) = two_gates();
+        let (mut new, y, out) = two_gates();
+        let squared = new.add_output_gate(GateType::Mul, out, y);
+
+        let diff = circuit_diff(&old, &new);
+        assert_eq!(
+            diff.added,
+            vec![GateSignature {
+                gate_type: GateType::Mul,
+                inputs: vec![out, y],
+                output: squared,
+            }]
+        );
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.added_outputs, vec![squared]);
+        assert!(diff.removed_outputs.is_empty());
+        assert_eq!(diff.to_string(), "+ gate Mul(w3, w1) -> w4\n+ output w4\n");
+
+        let reverse = circuit_diff(&new, &old);
+        assert_eq!(reverse.removed, diff.added);
+        assert!(reverse.added.is_empty());
+        assert!(circuit_diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn gates_are_matched_regardless_of_id() {
+        let (old, _, _) = two_gates();
+
+        // same gates added in the opposite order
+        let mut new = Circuit::new();
+        let x = new.add_input();
+        let y = new.add_input();
+        let sum = new.create_new_wire();
+        let out = new.create_new_wire();
+        new.add_gate(GateType::Mul, sum, x, out);
+        new.add_gate(GateType::Add, x, y, sum);
+        new.mark_output(out).unwrap();
+
+        assert!(circuit_diff(&old, &new).is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+//! Graphviz export of circuits, for looking at them while debugging.
+//!
+//! Input wires are boxes, constant wires are diamonds and output wires are double circles.
+//! Gates are circles labeled with their operation, named `gate<id>`.
+//! Every edge is one read of a wire, labeled with the wire id.
+//! It goes from the node producing the wire to the gate or output reading it.
+
+use crate::circuit::{Circuit, GateType, WireId};
+use std::io::{self, Write};
+
+impl Circuit {
+    /// Write the circuit as a Graphviz digraph.
+    /// A wire which is read but neither an input, a constant nor an output of a gate is drawn as a plain ellipse.
+    pub fn to_dot(&self, mut writer: impl Write) -> io::Result<()> {
+        let gates = self.get_all_gates();
+        let connections = self.wire_connections();
+
+        // node drawing each wire
+        let mut sources: Vec<Option<String>> = vec![None; self.get_wire_count()];
+        for (id, connection) in connections.iter().enumerate() {
+            if let Some(gate_id) = connection.from_id {
+                sources[id] = Some(format!("gate{}", gate_id));
+            }
+        }
+
+        writeln!(writer, "digraph circuit {{")?;
+        for input in self.get_all_inputs() {
+            writeln!(writer, "    w{0} [shape=box, label=\"w{0}\"];", input.0)?;
+            sources[input.0] = Some(format!("w{}", input.0));
+        }
+        for constant in self.get_constants() {
+            let wire = constant.get_wire_id().0;
+            writeln!(writer, "    w{0} [shape=diamond, label=\"w{0}\"];", wire)?;
+            sources[wire] = Some(format!("w{}", wire));
+        }
+        for (id, gate) in gates.iter().enumerate() {
+            let label = match gate.gate_type() {
+                GateType::Add => "+",
+                GateType::Sub => "-",
+                GateType::Mul => "*",
+                GateType::Neg => "neg",
+                GateType::ScalarMul => "*k",
+            };
+            writeln!(
+                writer,
+                "    gate{} [shape=circle, label=\"{}\"];",
+                id, label
+            )?;
+        }
+        for (i, output) in self.get_all_outputs().iter().enumerate() {
+            writeln!(
+                writer,
+                "    out{} [shape=doublecircle, label=\"w{}\"];",
+                i, output.0
+            )?;
+        }
+
+        let mut source = |writer: &mut dyn Write, wire: WireId| -> io::Result<String> {
+            if let Some(node) = &sources[wire.0] {
+                return Ok(node.clone());
+            }
+            let node = format!("w{}", wire.0);
+            writeln!(writer, "    {} [label=\"{}\"];", node, node)?;
+            sources[wire.0] = Some(node.clone());
+            Ok(node)
+        };
+        for (id, gate) in gates.iter().enumerate() {
+            for input in gate.get_inputs() {
+                let from = source(&mut writer, input)?;
+                writeln!(
+                    writer,
+                    "    {} -> gate{} [label=\"w{}\"];",
+                    from, id, input.0
+                )?;
+            }
+        }
+        for (i, output) in self.get_all_outputs().iter().enumerate() {
+            let from = source(&mut writer, *output)?;
+            writeln!(
+                writer,
+                "    {} -> out{} [label=\"w{}\"];",
+                from, i, output.0
+            )?;
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+
+    #[test]
+    fn render_multiple_outputs() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        let mut dot = vec![];
+        circuit.to_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(dot.contains("gate0 [shape=circle, label=\"+\"];"));
+        assert!(dot.contains("gate1 [shape=circle, label=\"*\"];"));
+        assert!(dot.contains("w0 [shape=box"));
+        // 4 gate inputs and 2 outputs
+        assert_eq!(dot.matches("->").count(), 6);
+        assert!(dot.contains("gate0 -> gate1 [label=\"w2\"];"));
+        assert!(dot.contains("gate1 -> out1 [label=\"w4\"];"));
+    }
+}
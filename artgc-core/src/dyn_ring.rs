@@ -0,0 +1,270 @@
+//! Ring whose modulus is chosen at runtime, e.g. from the command line,
+//! so that one build can evaluate circuits over several prime fields.
+//!
+//! Elements are reduced into a `u64`, so only primes below 2^64 are supported.
+//! Operations compute in `u128` and reduce with `%`, which is much slower than a dedicated field type.
+
+use crate::circuit::Circuit;
+use crate::eval_local::{eval_local, EvalLocalError};
+use crate::ring::Ring;
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Sub};
+
+/// Prime moduli supported by `DynRing`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Modulus {
+    /// 2^31 - 1
+    Mersenne31,
+    /// 2^61 - 1
+    Mersenne61,
+    /// 2^64 - 2^32 + 1
+    Goldilocks,
+}
+
+impl Modulus {
+    pub fn value(self) -> u64 {
+        match self {
+            Modulus::Mersenne31 => (1 << 31) - 1,
+            Modulus::Mersenne61 => (1 << 61) - 1,
+            Modulus::Goldilocks => 0xffff_ffff_0000_0001,
+        }
+    }
+}
+
+/// Element of the prime field of a `Modulus` picked at runtime.
+///
+/// `Ring::zero` and `Ring::one` don't know which field they are asked for,
+/// so they are `Integer`s, which take the modulus of the element they are combined with.
+/// Combining two elements of different moduli gives `Invalid`, and so does every operation on `Invalid`.
+/// Elements are compared by variant first, so `Integer(0)` is not equal to a field element 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DynRing {
+    /// `value` is always below `modulus.value()`.
+    Field {
+        value: u64,
+        modulus: Modulus,
+    },
+    /// Integer which is not bound to a modulus yet.
+    /// Arithmetic between two of them wraps around like `i64`.
+    Integer(i64),
+    Invalid,
+}
+
+impl DynRing {
+    /// Returns `value` modulo `modulus`.
+    pub fn new(value: u64, modulus: Modulus) -> Self {
+        DynRing::Field {
+            value: value % modulus.value(),
+            modulus,
+        }
+    }
+
+    /// Returns the modulus of a field element, or None for `Integer` and `Invalid`.
+    pub fn modulus(&self) -> Option<Modulus> {
+        match self {
+            DynRing::Field { modulus, .. } => Some(*modulus),
+            _ => None,
+        }
+    }
+
+    fn from_integer(value: i64, modulus: Modulus) -> u64 {
+        (value as i128).rem_euclid(modulus.value() as i128) as u64
+    }
+
+    /// Apply `field` to the values of both operands in a common modulus, or `integer` if both are integers.
+    fn combine(
+        self,
+        rhs: DynRing,
+        field: fn(u128, u128, u128) -> u128,
+        integer: fn(i64, i64) -> i64,
+    ) -> DynRing {
+        let (x, y, modulus) = match (self, rhs) {
+            (DynRing::Integer(x), DynRing::Integer(y)) => return DynRing::Integer(integer(x, y)),
+            (
+                DynRing::Field { value: x, modulus },
+                DynRing::Field {
+                    value: y,
+                    modulus: other,
+                },
+            ) if modulus == other => (x, y, modulus),
+            (DynRing::Field { value: x, modulus }, DynRing::Integer(y)) => {
+                (x, DynRing::from_integer(y, modulus), modulus)
+            }
+            (DynRing::Integer(x), DynRing::Field { value: y, modulus }) => {
+                (DynRing::from_integer(x, modulus), y, modulus)
+            }
+            _ => return DynRing::Invalid,
+        };
+        let p = modulus.value() as u128;
+        DynRing::Field {
+            value: field(x as u128, y as u128, p) as u64,
+            modulus,
+        }
+    }
+}
+
+impl Add for DynRing {
+    type Output = DynRing;
+
+    fn add(self, rhs: DynRing) -> DynRing {
+        self.combine(rhs, |x, y, p| (x + y) % p, i64::wrapping_add)
+    }
+}
+
+impl Sub for DynRing {
+    type Output = DynRing;
+
+    fn sub(self, rhs: DynRing) -> DynRing {
+        self.combine(rhs, |x, y, p| (x + p - y) % p, i64::wrapping_sub)
+    }
+}
+
+impl Mul for DynRing {
+    type Output = DynRing;
+
+    fn mul(self, rhs: DynRing) -> DynRing {
+        self.combine(rhs, |x, y, p| x * y % p, i64::wrapping_mul)
+    }
+}
+
+impl<'a> Add<&'a DynRing> for DynRing {
+    type Output = DynRing;
+
+    fn add(self, rhs: &'a DynRing) -> DynRing {
+        self + *rhs
+    }
+}
+
+impl<'a> Sub<&'a DynRing> for DynRing {
+    type Output = DynRing;
+
+    fn sub(self, rhs: &'a DynRing) -> DynRing {
+        self - *rhs
+    }
+}
+
+impl<'a> Mul<&'a DynRing> for DynRing {
+    type Output = DynRing;
+
+    fn mul(self, rhs: &'a DynRing) -> DynRing {
+        self * *rhs
+    }
+}
+
+impl Ring for DynRing {
+    fn zero() -> Self {
+        DynRing::Integer(0)
+    }
+
+    fn one() -> Self {
+        DynRing::Integer(1)
+    }
+}
+
+/// Evaluate a circuit like `eval_local`, over the field of the modulus of `input_values`.
+///
+/// Returns `EvalLocalError::ModulusMismatch` before evaluating if the input values and the `DynRing` constants
+/// of the circuit don't all have the same modulus, or if one of them is `DynRing::Invalid`.
+pub fn eval_local_dyn(
+    circuit: &Circuit,
+    input_values: Vec<DynRing>,
+) -> Result<Vec<DynRing>, EvalLocalError> {
+    let constants = circuit
+        .get_constants()
+        .iter()
+        .filter_map(|constant| constant.get_value::<DynRing>());
+    let mut modulus = None;
+    for value in input_values.iter().chain(constants) {
+        match value {
+            DynRing::Field { modulus: m, .. } => match modulus {
+                Some(modulus) if modulus != *m => return Err(EvalLocalError::ModulusMismatch),
+                _ => modulus = Some(*m),
+            },
+            DynRing::Integer(_) => {}
+            DynRing::Invalid => return Err(EvalLocalError::ModulusMismatch),
+        }
+    }
+
+    // every operand is in the same field or an integer, so no operation gives Invalid
+    eval_local(circuit, input_values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{eval_local_dyn, DynRing, Modulus};
+    use crate::circuit::*;
+    use crate::eval_local::EvalLocalError;
+
+    // out = (x - y) * x
+    fn circuit() -> Circuit {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let diff = circuit.create_new_wire();
+        circuit.add_gate(GateType::Sub, x, y, diff);
+        circuit.add_output_gate(GateType::Mul, diff, x);
+        circuit
+    }
+
+    #[test]
+    fn same_circuit_under_two_moduli() {
+        let circuit = circuit();
+
+        // (2^30 - (2^30 + 2)) * 2^30 = -2^31 = -1 modulo 2^31 - 1
+        let m31 = |value| DynRing::new(value, Modulus::Mersenne31);
+        assert_eq!(
+            eval_local_dyn(&circuit, vec![m31(1 << 30), m31((1 << 30) + 2)]),
+            Ok(vec![m31((1 << 31) - 2)])
+        );
+
+        // no reduction happens below 2^61 - 1 other than for the negative difference
+        let m61 = |value| DynRing::new(value, Modulus::Mersenne61);
+        assert_eq!(
+            eval_local_dyn(&circuit, vec![m61(1 << 30), m61((1 << 30) + 2)]),
+            Ok(vec![m61((1 << 61) - 1 - (1 << 31))])
+        );
+    }
+
+    #[test]
+    fn neg_takes_the_modulus_of_its_input() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let neg = circuit.add_neg(x);
+        circuit.mark_output(neg).unwrap();
+
+        let goldilocks = Modulus::Goldilocks;
+        assert_eq!(
+            eval_local_dyn(&circuit, vec![DynRing::new(1, goldilocks)]),
+            Ok(vec![DynRing::new(goldilocks.value() - 1, goldilocks)])
+        );
+    }
+
+    #[test]
+    fn mismatched_moduli_are_rejected() {
+        let circuit = circuit();
+        let inputs = vec![
+            DynRing::new(1, Modulus::Mersenne31),
+            DynRing::new(1, Modulus::Mersenne61),
+        ];
+        assert_eq!(
+            eval_local_dyn(&circuit, inputs),
+            Err(EvalLocalError::ModulusMismatch)
+        );
+
+        let mut circuit = circuit;
+        let x = circuit.get_all_inputs()[0];
+        let two = circuit.create_constant_wire(DynRing::new(2, Modulus::Goldilocks));
+        circuit.add_output_gate(GateType::Mul, x, two);
+        let inputs = vec![DynRing::new(1, Modulus::Mersenne31); 2];
+        assert_eq!(
+            eval_local_dyn(&circuit, inputs),
+            Err(EvalLocalError::ModulusMismatch)
+        );
+
+        // operators can't fail, so they give Invalid instead
+        assert_eq!(
+            DynRing::new(1, Modulus::Mersenne31) + DynRing::new(1, Modulus::Mersenne61),
+            DynRing::Invalid
+        );
+    }
+}
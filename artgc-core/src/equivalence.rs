@@ -0,0 +1,270 @@
+//! Probabilistic equivalence check of two circuits.
+//!
+//! An arithmetic circuit computes a polynomial of its inputs.
+//! If two circuits compute different polynomials, the difference is a nonzero polynomial of degree at most d,
+//! and by the Schwartz-Zippel lemma it evaluates to zero on a uniformly random point of F^n
+//! with probability at most d / |F|.
+//! Evaluating both circuits on `trials` independent random points bounds the chance of a false pass
+//! by (d / |F|)^trials.
+
+use crate::circuit::{Circuit, GateType};
+use crate::error::{CircuitError, CircuitResult};
+use crate::eval_local::eval_local;
+use crate::ring::{Ring, SampleRing};
+use ff::PrimeField;
+use rand_core::RngCore;
+use std::cmp::max;
+
+impl Circuit {
+    /// Returns the total degree of the polynomial computed by the circuit,
+    /// which is the maximum degree over all the output wires.
+    /// Input wires have degree 1. Add, Sub and Neg gates take the max of their input degrees, Mul and ScalarMul gates the sum.
+    /// Constant wires have degree 0, and other wires which are not produced by any gate are treated as variables.
+    pub fn degree(&self) -> CircuitResult<usize> {
+        let wire_count = self.get_wire_count();
+        let gates = self.get_all_gates();
+
+        let mut producers = vec![None; wire_count];
+        for (id, gate) in gates.iter().enumerate() {
+            producers[gate.get_output().0] = Some(id);
+        }
+
+        let mut degrees: Vec<Option<usize>> = vec![None; wire_count];
+        for constant in self.get_constants() {
+            degrees[constant.get_wire_id().0] = Some(0);
+        }
+        // wires whose inputs are being resolved. Reaching one of them again means a cycle.
+        let mut in_progress = vec![false; wire_count];
+
+        for out in self.get_all_outputs() {
+            let mut stack = vec![out.0];
+            while let Some(&wire) = stack.last() {
+                if degrees[wire].is_some() {
+                    stack.pop();
+                    continue;
+                }
+
+                let gate_id = match producers[wire] {
+                    Some(gate_id) => gate_id,
+                    None => {
+                        degrees[wire] = Some(1);
+                        stack.pop();
+                        continue;
+                    }
+                };
+                let gate = &gates[gate_id];
+                let inputs = gate.get_inputs();
+
+                let input_degrees: Option<Vec<usize>> =
+                    inputs.iter().map(|input| degrees[input.0]).collect();
+                match input_degrees {
+                    Some(input_degrees) => {
+                        degrees[wire] = Some(match gate.gate_type() {
+                            GateType::Add | GateType::Sub | GateType::Neg => {
+                                input_degrees.into_iter().max().unwrap()
+                            }
+                            // the scalar of ScalarMul is a constant wire, which has degree 0
+                            GateType::Mul | GateType::ScalarMul => input_degrees.into_iter().sum(),
+                        });
+                        in_progress[wire] = false;
+                        stack.pop();
+                    }
+                    None => {
+                        in_progress[wire] = true;
+                        for input in inputs {
+                            if degrees[input.0].is_none() {
+                                if in_progress[input.0] {
+                                    return Err(CircuitError::CyclicPath {
+                                        gate_id,
+                                        wire_id: input.0,
+                                    });
+                                }
+                                stack.push(input.0);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(self
+            .get_all_outputs()
+            .iter()
+            .map(|out| degrees[out.0].unwrap_or(0))
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// Upper bound on the probability that `probably_equivalent` passes with `trials` trials
+    /// although the two circuits are not equivalent.
+    /// Computed as (d / 2^(NUM_BITS - 1))^trials where d is the larger degree of the two circuits.
+    pub fn equivalence_soundness_error<T: PrimeField>(
+        &self,
+        other: &Circuit,
+        trials: usize,
+    ) -> CircuitResult<f64> {
+        let degree = max(self.degree()?, other.degree()?) as f64;
+        let field_size = 2f64.powi(T::NUM_BITS as i32 - 1);
+        let per_trial = (degree / field_size).min(1.0);
+
+        Ok(per_trial.powi(trials as i32))
+    }
+
+    /// Check if two circuits compute the same function by evaluating both on `trials` random field points.
+    /// Returns false as soon as one point gives different outputs, or if either circuit can't be evaluated.
+    /// A pass is not a proof. See `equivalence_soundness_error` for the probability of a false pass.
+    /// Same as `circuits_equivalent`.
+    pub fn probably_equivalent<T: Ring + PrimeField, R: RngCore>(
+        &self,
+        other: &Circuit,
+        trials: usize,
+        rng: &mut R,
+    ) -> bool {
+        circuits_equivalent::<T>(self, other, trials, rng)
+    }
+}
+
+/// Check if circuits `a` and `b` compute the same function by evaluating both on `trials` random inputs.
+/// Returns false if their numbers of inputs or outputs differ, as soon as one input vector gives different outputs,
+/// or if either circuit can't be evaluated.
+///
+/// This is a probabilistic check, not a proof: circuits which differ only on few inputs likely pass.
+/// Over a prime field, see `Circuit::equivalence_soundness_error` for the probability of a false pass.
+pub fn circuits_equivalent<T: SampleRing>(
+    a: &Circuit,
+    b: &Circuit,
+    trials: usize,
+    rng: &mut impl RngCore,
+) -> bool {
+    if a.get_all_inputs().len() != b.get_all_inputs().len()
+        || a.get_all_outputs().len() != b.get_all_outputs().len()
+    {
+        return false;
+    }
+
+    // degree computation also rejects cyclic circuits, which eval_local can't handle.
+    if a.degree().is_err() || b.degree().is_err() {
+        return false;
+    }
+
+    for _ in 0..trials {
+        let inputs: Vec<T> = (0..a.get_all_inputs().len())
+            .map(|_| T::random(rng))
+            .collect();
+
+        match (eval_local(a, inputs.clone()), eval_local(b, inputs)) {
+            (Ok(a), Ok(b)) if a == b => continue,
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::circuits_equivalent;
+    use crate::circuit::*;
+    use crate::test_utils::{Fp, TestRng};
+
+    // out = (x + y) * z
+    fn factored() -> Circuit {
+        let mut circuit = Circuit::new();
+        let x = circuit.create_new_wire();
+        let y = circuit.create_new_wire();
+        let z = circuit.create_new_wire();
+        let sum = circuit.create_new_wire();
+        let out = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        circuit.add_gate(GateType::Mul, sum, z, out);
+
+        circuit.mark_input(x).unwrap();
+        circuit.mark_input(y).unwrap();
+        circuit.mark_input(z).unwrap();
+        circuit.mark_output(out).unwrap();
+        circuit
+    }
+
+    // out = x * z + y * z
+    // with `second` set to GateType::Mul, it becomes x * z * y * z instead.
+    fn expanded(second: GateType) -> Circuit {
+        let mut circuit = Circuit::new();
+        let x = circuit.create_new_wire();
+        let y = circuit.create_new_wire();
+        let z = circuit.create_new_wire();
+        let xz = circuit.create_new_wire();
+        let yz = circuit.create_new_wire();
+        let out = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, x, z, xz);
+        circuit.add_gate(GateType::Mul, y, z, yz);
+        circuit.add_gate(second, xz, yz, out);
+
+        circuit.mark_input(x).unwrap();
+        circuit.mark_input(y).unwrap();
+        circuit.mark_input(z).unwrap();
+        circuit.mark_output(out).unwrap();
+        circuit
+    }
+
+    #[test]
+    fn degree_of_circuit() {
+        assert_eq!(factored().degree(), Ok(2));
+        assert_eq!(expanded(GateType::Mul).degree(), Ok(4));
+    }
+
+    #[test]
+    fn equal_circuits_are_probably_equivalent() {
+        let mut rng = TestRng(0x5eed);
+        let a = factored();
+        let b = expanded(GateType::Add);
+        assert!(
+            a.probably_equivalent::<Fp, _>(&b, 8, &mut rng),
+            "(x + y) * z and x * z + y * z should be equivalent"
+        );
+
+        let error = a.equivalence_soundness_error::<Fp>(&b, 8).unwrap();
+        assert!(error < 1e-100, "Soundness error should be negligible");
+    }
+
+    #[test]
+    fn differing_circuits_are_not_equivalent() {
+        let mut rng = TestRng(0x5eed);
+        let a = factored();
+        let b = expanded(GateType::Mul);
+        assert!(
+            !a.probably_equivalent::<Fp, _>(&b, 8, &mut rng),
+            "(x + y) * z and x * z * y * z should not be equivalent"
+        );
+    }
+
+    #[test]
+    fn commuted_add_is_equivalent_but_mul_is_not() {
+        // out = x op y, with the inputs of the gate swapped if `swap` is set
+        fn binary(gate_type: GateType, swap: bool) -> Circuit {
+            let mut circuit = Circuit::new();
+            let x = circuit.add_input();
+            let y = circuit.add_input();
+            if swap {
+                circuit.add_output_gate(gate_type, y, x);
+            } else {
+                circuit.add_output_gate(gate_type, x, y);
+            }
+            circuit
+        }
+
+        let mut rng = TestRng(0x5eed);
+        let sum = binary(GateType::Add, false);
+        assert!(circuits_equivalent::<Fp>(
+            &sum,
+            &binary(GateType::Add, true),
+            8,
+            &mut rng
+        ));
+        assert!(!circuits_equivalent::<Fp>(
+            &sum,
+            &binary(GateType::Mul, false),
+            8,
+            &mut rng
+        ));
+    }
+}
@@ -1,18 +1,54 @@
-use std::fmt::Display;
+use crate::circuit::GateType;
+use crate::eval_local::EvalLocalError;
+use alloc::string::String;
+use core::fmt::Display;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum CircuitError {
     EmptyInput,
     EmptyOutput,
     CyclicPath { gate_id: usize, wire_id: usize },
+    UndrivenWire { wire_id: usize },
+    UnknownWire { wire_id: usize },
+    UnknownGate { gate_id: usize },
+    DuplicateInput { wire_id: usize },
+    InvalidGraphNode { node_index: usize },
+    TraceMismatch { wire_id: usize },
+    InputLengthMismatch { expected: usize, got: usize },
+    AssignmentLengthMismatch { expected: usize, got: usize },
+    SelfLoop { gate_id: usize },
+    AnalysisLimitExceeded { limit: usize },
+    CircuitTooLarge { gate_count: usize, limit: usize },
+    CapacityExceeded { capacity: Capacity, limit: usize },
+    DanglingWire { wire_id: usize },
+    UnreachableWire { wire_id: usize },
+    BristolParse { line: usize, message: String },
+    EvaluationFailed(EvalLocalError),
+    ArityMismatch { gate_type: GateType, got: usize },
+}
+
+/// What `CircuitError::CapacityExceeded` ran out of. The circuit already has as many wires or gates
+/// as its limit, see `Circuit::with_capacity_limits`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Capacity {
+    Wires,
+    Gates,
 }
 
 pub type CircuitResult<E> = Result<E, CircuitError>;
 
+/// Keeps the evaluation error as it is, so that `?` can mix evaluation with building and checking circuits.
+impl From<EvalLocalError> for CircuitError {
+    fn from(error: EvalLocalError) -> Self {
+        CircuitError::EvaluationFailed(error)
+    }
+}
+
+#[cfg(feature = "std")]
 impl std::error::Error for CircuitError {}
 
 impl Display for CircuitError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match &self {
             CircuitError::EmptyInput => {
                 write!(f, "This circuit has no input.")
@@ -27,6 +63,93 @@ impl Display for CircuitError {
                     gate_id, wire_id
                 )
             }
+            CircuitError::UndrivenWire { wire_id } => {
+                write!(
+                    f,
+                    "Wire with id{} is read by a gate, but it is neither an input of the circuit nor an output of any gate.",
+                    wire_id
+                )
+            }
+            CircuitError::UnknownWire { wire_id } => {
+                write!(f, "Wire with id{} does not exist in this circuit.", wire_id)
+            }
+            CircuitError::UnknownGate { gate_id } => {
+                write!(f, "Gate with id{} does not exist in this circuit.", gate_id)
+            }
+            CircuitError::DuplicateInput { wire_id } => {
+                write!(
+                    f,
+                    "Wire with id{} is already an input of this circuit.",
+                    wire_id
+                )
+            }
+            CircuitError::InvalidGraphNode { node_index } => {
+                write!(
+                    f,
+                    "Graph node with index{} can't be converted into a part of circuit.",
+                    node_index
+                )
+            }
+            CircuitError::TraceMismatch { wire_id } => {
+                write!(
+                    f,
+                    "Recorded value of wire with id{} does not match the evaluation.",
+                    wire_id
+                )
+            }
+            CircuitError::InputLengthMismatch { expected, got } => {
+                write!(
+                    f,
+                    "This circuit has {} input wires, but {} values are given.",
+                    expected, got
+                )
+            }
+            CircuitError::AssignmentLengthMismatch { expected, got } => {
+                write!(
+                    f,
+                    "This circuit has {} wires, but {} values are assigned.",
+                    expected, got
+                )
+            }
+            CircuitError::SelfLoop { gate_id } => {
+                write!(f, "Gate with id{} reads its own output wire.", gate_id)
+            }
+            CircuitError::AnalysisLimitExceeded { limit } => {
+                write!(f, "Analysis was stopped after {} steps.", limit)
+            }
+            CircuitError::CircuitTooLarge { gate_count, limit } => {
+                write!(
+                    f,
+                    "This circuit has {} gates, but at most {} are supported.",
+                    gate_count, limit
+                )
+            }
+            CircuitError::CapacityExceeded { capacity, limit } => {
+                let what = match capacity {
+                    Capacity::Wires => "wires",
+                    Capacity::Gates => "gates",
+                };
+                write!(f, "This circuit can't have more than {} {}.", limit, what)
+            }
+            CircuitError::DanglingWire { wire_id } => {
+                write!(f, "Wire with id{} is not connected to anything.", wire_id)
+            }
+            CircuitError::UnreachableWire { wire_id } => {
+                write!(f, "Wire with id{} has no path to any output wire.", wire_id)
+            }
+            CircuitError::BristolParse { line, message } => {
+                write!(
+                    f,
+                    "Bristol circuit is invalid at line {}: {}.",
+                    line, message
+                )
+            }
+            CircuitError::EvaluationFailed(error) => {
+                write!(f, "Evaluation failed: {:?}.", error)
+            }
+            CircuitError::ArityMismatch { gate_type, got } => {
+                write!(f, "{:?} gate can't have {} inputs.", gate_type, got)
+            }
         }
     }
 }
@@ -0,0 +1,143 @@
+//! Demand-driven evaluation of a circuit.
+//! Starting from the requested wires, values are pulled backwards through the gates producing them,
+//! so gates which none of the requested wires depend on are never evaluated.
+
+use crate::circuit::{Circuit, WireId};
+use crate::error::{CircuitError, CircuitResult};
+use crate::ring::Ring;
+
+impl Circuit {
+    /// Evaluate only the gates needed to compute `targets`, and return their values in the same order.
+    /// Every wire is evaluated at most once, and its value is reused by all the gates reading it.
+    ///
+    /// Returns an error if a target doesn't exist, if a needed wire is not driven by anything,
+    /// or if a needed gate is on a cycle.
+    pub fn eval_lazy<T: Ring>(&self, inputs: Vec<T>, targets: &[WireId]) -> CircuitResult<Vec<T>> {
+        let all_inputs = self.get_all_inputs();
+        if inputs.len() != all_inputs.len() {
+            return Err(CircuitError::InputLengthMismatch {
+                expected: all_inputs.len(),
+                got: inputs.len(),
+            });
+        }
+
+        let gates = self.get_all_gates();
+        let connections = self.wire_connections();
+        let mut values: Vec<Option<T>> = vec![None; self.get_wire_count()];
+        for (wire, value) in all_inputs.iter().zip(inputs) {
+            values[wire.0] = Some(value);
+        }
+        for constant in self.get_constants() {
+            values[constant.get_wire_id().0] = constant.get_value::<T>().cloned();
+        }
+
+        // wires whose operands have been requested, but which are not evaluated yet.
+        // they are exactly the wires on the path from the current target.
+        let mut in_progress = vec![false; self.get_wire_count()];
+
+        for target in targets {
+            if target.0 < self.get_wire_offset() || target.0 >= self.get_wire_count() {
+                return Err(CircuitError::UnknownWire { wire_id: target.0 });
+            }
+
+            let mut stack = vec![target.0];
+            while let Some(&w) = stack.last() {
+                if values[w].is_some() {
+                    stack.pop();
+                    continue;
+                }
+                let gate_id = connections[w]
+                    .from_id
+                    .ok_or(CircuitError::UndrivenWire { wire_id: w })?;
+                let gate = &gates[gate_id];
+                let operands = gate.get_inputs();
+
+                if in_progress[w] {
+                    // all operands are evaluated, since they were pushed above this wire.
+                    let operand_values = operands.iter().map(|x| values[x.0].clone().unwrap());
+                    values[w] = Some(gate.evaluate(operand_values));
+                    in_progress[w] = false;
+                    stack.pop();
+                    continue;
+                }
+
+                in_progress[w] = true;
+                for operand in operands {
+                    if values[operand.0].is_none() {
+                        if in_progress[operand.0] {
+                            return Err(CircuitError::CyclicPath {
+                                gate_id,
+                                wire_id: operand.0,
+                            });
+                        }
+                        stack.push(operand.0);
+                    }
+                }
+            }
+        }
+
+        Ok(targets
+            .iter()
+            .map(|target| values[target.0].clone().unwrap())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::test_utils::Counted;
+
+    #[test]
+    fn lazy_evaluates_only_needed_gates() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        // out3 = out2 * out2
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let in3 = circuit.add_input();
+        let out1 = circuit.add_output_gate(GateType::Add, in1, in2);
+        let out2 = circuit.add_output_gate(GateType::Mul, out1, in3);
+        let out3 = circuit.add_output_gate(GateType::Mul, out2, out2);
+
+        let inputs = || vec![Counted(1), Counted(2), Counted(3)];
+
+        Counted::reset();
+        assert_eq!(circuit.eval_lazy(inputs(), &[out1]), Ok(vec![Counted(3)]));
+        assert_eq!(
+            Counted::operations(),
+            1,
+            "Only the add gate should be evaluated"
+        );
+
+        Counted::reset();
+        assert_eq!(
+            circuit.eval_lazy(inputs(), &[out3, out2]),
+            Ok(vec![Counted(81), Counted(9)])
+        );
+        assert_eq!(
+            Counted::operations(),
+            3,
+            "Each gate should be evaluated once"
+        );
+    }
+
+    #[test]
+    fn lazy_reports_undriven_wire() {
+        let mut circuit = Circuit::new();
+        let in1 = circuit.add_input();
+        let floating = circuit.create_new_wire();
+        let out = circuit.add_output_gate(GateType::Add, in1, floating);
+
+        assert_eq!(
+            circuit.eval_lazy(vec![Counted(1)], &[out]),
+            Err(CircuitError::UndrivenWire {
+                wire_id: floating.0
+            })
+        );
+    }
+}
@@ -1,146 +1,620 @@
-use crate::circuit::{Circuit, GateType};
+use crate::circuit::{Circuit, WireId};
+use crate::error::CircuitError;
 use crate::ring::Ring;
-use std::cmp::max;
+use crate::topo::topological_sort;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum EvalLocalError {
-    EmptyWire,
+    /// Wires left without a value, in wire id order. They are on a cycle, depend on a wire
+    /// which is neither an input nor driven by a gate, or are such a wire themselves.
+    EmptyWire { wire_ids: Vec<usize> },
+    /// Number of input values doesn't match the number of input wires of the circuit.
+    InputLengthMismatch { expected: usize, got: usize },
+    /// Output wire whose value can't be computed from the inputs.
+    UndrivenOutput { wire_id: usize },
+    /// Values of different rings were combined, see `eval_local_dyn`.
+    ModulusMismatch,
 }
 
-/// In order to keep track of wire value and layer
-/// evaluating the gate requires the all the input wires
-/// have to have actual values
-/// Wires marked as inputs of the circuit have layer as 0
-/// Output wires of gates have layer number of max(input1_layer, input2_layer) + 1.
-#[derive(Clone, Copy, Debug)]
-struct Wire<T: Ring> {
-    pub layer: Option<usize>,
-    pub value: Option<T>,
+/// Layer numbers of gates and wires, computed by `label_wires_with_layer`.
+/// Evaluating a gate requires all of its input wires to have values,
+/// so gates are evaluated layer after layer.
+/// Wires marked as inputs of the circuit and constant wires have layer 0.
+/// Output wires of gates have layer number of max(input layers) + 1.
+/// Every gate whose input wires all have layers is in a layer, including a gate driving a wire
+/// which another gate drives too. Such a wire takes the layer and, on evaluation, the value of the first of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layers {
+    /// Gate ids grouped by layer number, which is the index of the outer vector.
+    pub gate_layers: Vec<Vec<usize>>,
+    /// Layer of each wire indexed by wire id, or None if the wire can't be evaluated.
+    pub wire_layers: Vec<Option<usize>>,
 }
 
 /// Returns information related to layer of gates and wire
 /// scan the circuit and put layer number to all gates and wires.
-/// First element of returend tuple is vector of vector of gate_id.
-/// Gates are grouped with layer number represented with index of outer vector.
-fn label_wires_with_layer<T: Ring>(circuit: &Circuit) -> (Vec<Vec<usize>>, Vec<Wire<T>>) {
-    let mut wires = vec![
-        Wire {
-            layer: None,
-            value: None
-        };
-        circuit.get_wire_count()
-    ];
+/// Prefer `Circuit::layers`, which caches the result.
+pub(crate) fn label_wires_with_layer(circuit: &Circuit) -> Layers {
+    let mut wire_layers: Vec<Option<usize>> = vec![None; circuit.get_wire_count()];
 
     let mut gate_layers: Vec<Vec<usize>> = vec![];
 
     // put 0 to input layer
     let input_wires = circuit.get_all_inputs();
     for input in input_wires {
-        wires[input.0].layer = Some(0);
+        wire_layers[input.0] = Some(0);
+    }
+
+    // constants are known before evaluation, so they are in the input layer too
+    for constant in circuit.get_constants() {
+        wire_layers[constant.get_wire_id().0] = Some(0);
     }
 
     let gates = circuit.get_all_gates();
 
-    // iterate through gates until all of the wire has layer value
-    // TODO: optimize iteration
-    let mut i = 0;
-    while !wires.iter().all(|w| w.layer.is_some()) {
-        let wire_id = gates[i].get_output();
-        let wire = wires[wire_id.0];
-
-        if !wire.layer.is_some() {
-            // check if the two input wires of the gate has layer or not
-            let (x, y) = gates[i].get_inputs();
-            let x_layer = wires[x.0].layer;
-            let y_layer = wires[y.0].layer;
-            if x_layer.is_some() && y_layer.is_some() {
-                let current_layer = max(x_layer.unwrap(), y_layer.unwrap());
-                wires[wire_id.0].layer = Some(current_layer + 1);
-
-                // TODO: possible skip if optimization is set to true
-                // provide max layer number using config file
-                if gate_layers.get(current_layer).is_none() {
-                    gate_layers.resize(current_layer + 1, Vec::<usize>::new());
-                }
-                gate_layers.get_mut(current_layer).unwrap().push(i);
+    // Kahn's algorithm: a gate is ready once all of its input wires have layers,
+    // so each gate is processed exactly once.
+    let mut consumers: Vec<Vec<usize>> = vec![vec![]; wire_layers.len()];
+    // number of input wires of each gate which don't have a layer yet
+    let mut pending = vec![0; gates.len()];
+    let mut ready = VecDeque::new();
+    for (i, gate) in gates.iter().enumerate() {
+        for input in gate.get_inputs() {
+            // a wire read several times by the same gate is counted once
+            if consumers[input.0].last() == Some(&i) {
+                continue;
+            }
+            consumers[input.0].push(i);
+            if wire_layers[input.0].is_none() {
+                pending[i] += 1;
             }
         }
+        if pending[i] == 0 {
+            ready.push_back(i);
+        }
+    }
+
+    while let Some(i) = ready.pop_front() {
+        let wire_id = gates[i].get_output();
+
+        // all input wires have layers since the gate has no pending inputs
+        let current_layer = gates[i]
+            .get_inputs()
+            .iter()
+            .filter_map(|w| wire_layers[w.0])
+            .max()
+            .unwrap();
+
+        // TODO: possible skip if optimization is set to true
+        // provide max layer number using config file
+        if gate_layers.len() <= current_layer {
+            gate_layers.resize(current_layer + 1, Vec::<usize>::new());
+        }
+        gate_layers[current_layer].push(i);
 
-        if i == wires.len() - 1 {
-            i = 0;
-        } else {
-            i += 1;
+        // a wire driven by several gates takes the layer of the first one
+        if wire_layers[wire_id.0].is_none() {
+            wire_layers[wire_id.0] = Some(current_layer + 1);
+
+            for next in consumers[wire_id.0].iter() {
+                pending[*next] -= 1;
+                if pending[*next] == 0 {
+                    ready.push_back(*next);
+                }
+            }
         }
     }
 
-    return (gate_layers, wires);
+    // gates on a cycle or reading an undriven wire never get ready,
+    // and their output wires are left without layers.
+    Layers {
+        gate_layers,
+        wire_layers,
+    }
 }
 
 /// This method simply evaluates a given circuit with given inputs locally.
 /// It doesn't involve any circuit garbling or networking operations.
 /// Mostly used for debugging purpose
+///
+/// The circuit is expected to have passed `Circuit::is_valid` first.
+/// Gates on a cycle can never be evaluated, so a cyclic circuit fails with `EvalLocalError::EmptyWire`
+/// listing the wires on the cycle and the ones depending on it.
 pub fn eval_local<T: Ring>(
     circuit: &Circuit,
     input_values: Vec<T>,
 ) -> Result<Vec<T>, EvalLocalError> {
-    // variable to keep track of actual wire values of type T and layer number
-    // put layer number to all layers and gates
-    let (gate_layers, mut wires) = label_wires_with_layer::<T>(circuit);
-    println!("gate_layers: {:?}, wires: {:?}", gate_layers, wires);
+    eval_local_with_constants(circuit, input_values, constant_values(circuit))
+}
 
-    let all_gates = circuit.get_all_gates();
-    let all_inputs = circuit.get_all_inputs();
+/// Same as `eval_local`, with `constants` as the values of the wires indexed by wire id before the inputs are set,
+/// for rings whose constant values are derived from constants stored with another ring.
+pub(crate) fn eval_local_with_constants<T: Ring>(
+    circuit: &Circuit,
+    input_values: Vec<T>,
+    constants: Vec<Option<T>>,
+) -> Result<Vec<T>, EvalLocalError> {
+    check_input_length(circuit, &input_values)?;
+    let layers = circuit.layers();
+    let (values, empty) =
+        evaluate_layers(circuit, &layers.gate_layers, constants, input_values, false);
+    check_empty_wires(empty)?;
+    Ok(take_outputs(circuit, values))
+}
 
-    // put value to input wires
-    for (i, wire_id) in all_inputs.into_iter().enumerate() {
-        let wire = wires.get_mut(wire_id.0).unwrap();
-        wire.value = Some(input_values[i]);
+/// Same as `eval_local` for each of `input_values`, but the layering of the circuit is only looked up once for the whole batch.
+/// Every input vector is checked to have the right length before any of them is evaluated.
+pub fn eval_local_batch<T: Ring>(
+    circuit: &Circuit,
+    input_values: &[Vec<T>],
+) -> Result<Vec<Vec<T>>, EvalLocalError> {
+    for inputs in input_values {
+        check_input_length(circuit, inputs)?;
     }
 
-    for current_layer in 0..(gate_layers.len()) {
-        for gate_id in gate_layers[current_layer].iter() {
-            let gate = &all_gates[*gate_id];
-            let (in1, in2) = gate.get_inputs();
-            let out = gate.get_output();
+    let layers = circuit.layers();
+    input_values
+        .iter()
+        .map(|inputs| {
+            let (values, empty) = evaluate_layers(
+                circuit,
+                &layers.gate_layers,
+                constant_values(circuit),
+                inputs.clone(),
+                false,
+            );
+            check_empty_wires(empty)?;
+            Ok(take_outputs(circuit, values))
+        })
+        .collect()
+}
+
+/// Move the values of the output wires out of `values`, in output order.
+/// Every output wire must have a value.
+fn take_outputs<T: Ring>(circuit: &Circuit, mut values: Vec<Option<T>>) -> Vec<T> {
+    let all_outputs = circuit.get_all_outputs();
+    let mut remaining_reads = vec![0; values.len()];
+    for out in all_outputs {
+        remaining_reads[out.0] += 1;
+    }
 
-            let in1 = wires.get(in1.0).unwrap().value.unwrap();
-            let in2 = wires.get(in2.0).unwrap().value.unwrap();
-            let mut out = wires.get_mut(out.0).unwrap();
+    all_outputs
+        .iter()
+        .map(|out| read_wire(&mut values, &mut remaining_reads, *out).unwrap())
+        .collect()
+}
 
-            out.value = match gate.gate_type() {
-                GateType::Add => Some(in1 + in2),
-                GateType::Mul => Some(in1 * in2),
-            };
+/// Same as `eval_local`, but returns the values of the named outputs by name.
+/// Outputs without a name are evaluated, but not returned.
+#[cfg(feature = "std")]
+pub fn eval_local_named<T: Ring>(
+    circuit: &Circuit,
+    input_values: Vec<T>,
+) -> Result<HashMap<String, T>, EvalLocalError> {
+    let values = eval_local(circuit, input_values)?;
+    let outputs = circuit.get_all_outputs();
+    Ok(circuit
+        .get_output_names()
+        .iter()
+        .map(|(name, wire)| {
+            // named outputs are marked as outputs, so they have a position
+            let i = outputs.iter().position(|out| out == wire).unwrap();
+            (name.clone(), values[i].clone())
+        })
+        .collect())
+}
+
+/// Same as `eval_local`, but returns the values of all the wires indexed by wire id instead of only the outputs,
+/// so that intermediate values can be inspected when a circuit computes something unexpected.
+/// Every wire of the circuit has a value. Only ids below the wire offset of the circuit are None.
+///
+/// Values are cloned instead of being moved at their last read, so this is slower than `eval_local`.
+pub fn eval_local_trace<T: Ring>(
+    circuit: &Circuit,
+    input_values: Vec<T>,
+) -> Result<Vec<Option<T>>, EvalLocalError> {
+    check_input_length(circuit, &input_values)?;
+    let (values, empty) = evaluate_wires(circuit, input_values, true);
+    check_empty_wires(empty)?;
+
+    Ok(values)
+}
+
+/// Same as `eval_local`, but gates are evaluated one at a time in the order of `topological_sort`,
+/// without computing the layers of the circuit.
+/// Every wire counts the gates reading it, and its value is dropped as soon as the last of them is evaluated.
+/// So only the values still to be read are held at once, which is the width of the circuit
+/// rather than its wire count. Counts and evaluated flags are still kept for every wire.
+///
+/// A cyclic circuit fails with `EvalLocalError::EmptyWire` listing a wire on the cycle.
+pub fn eval_local_streaming<T: Ring>(
+    circuit: &Circuit,
+    input_values: Vec<T>,
+) -> Result<Vec<T>, EvalLocalError> {
+    check_input_length(circuit, &input_values)?;
+    let order = topological_sort(circuit).map_err(|e| EvalLocalError::EmptyWire {
+        wire_ids: match e {
+            CircuitError::CyclicPath { wire_id, .. } => vec![wire_id],
+            _ => vec![],
+        },
+    })?;
+    let gates = circuit.get_all_gates();
+
+    // output positions are read once more at the end
+    let mut remaining_reads = vec![0; circuit.get_wire_count()];
+    for gate in gates {
+        for input in gate.get_inputs() {
+            remaining_reads[input.0] += 1;
+        }
+    }
+    for out in circuit.get_all_outputs() {
+        remaining_reads[out.0] += 1;
+    }
+
+    // values which are still to be read, by wire id
+    let mut live: BTreeMap<usize, T> = BTreeMap::new();
+    let mut assigned = vec![false; circuit.get_wire_count()];
+    let known = circuit
+        .get_constants()
+        .iter()
+        .filter_map(|constant| Some((constant.get_wire_id(), constant.get_value::<T>()?.clone())))
+        .chain(circuit.get_all_inputs().iter().copied().zip(input_values));
+    for (wire_id, value) in known {
+        assigned[wire_id.0] = true;
+        if remaining_reads[wire_id.0] > 0 {
+            live.insert(wire_id.0, value);
+        }
+    }
+
+    for id in order {
+        let gate = &gates[id];
+        let out = gate.get_output().0;
+
+        // every input is read, even after a missing one, so that read counts stay in sync
+        let inputs: Vec<Option<T>> = gate
+            .get_inputs()
+            .into_iter()
+            .map(|input| read_live(&mut live, &mut remaining_reads, input))
+            .collect();
+
+        if assigned[out] {
+            continue;
+        }
+        if let Some(inputs) = inputs.into_iter().collect::<Option<Vec<T>>>() {
+            assigned[out] = true;
+            if remaining_reads[out] > 0 {
+                live.insert(out, gate.evaluate(inputs));
+            }
         }
     }
 
-    // check if all the gates are evaluated
-    let res = wires.iter().all(|w| w.value.is_some() && w.layer.is_some());
-    if !res {
-        return Err(EvalLocalError::EmptyWire);
+    check_empty_wires(
+        (circuit.get_wire_offset()..assigned.len())
+            .filter(|wire_id| !assigned[*wire_id])
+            .collect(),
+    )?;
+
+    // every output wire has been assigned, and is kept until its last output position
+    Ok(circuit
+        .get_all_outputs()
+        .iter()
+        .map(|out| read_live(&mut live, &mut remaining_reads, *out).unwrap())
+        .collect())
+}
+
+/// Same as `read_wire`, for values held in a map by wire id.
+fn read_live<T: Ring>(
+    live: &mut BTreeMap<usize, T>,
+    remaining_reads: &mut [usize],
+    wire_id: WireId,
+) -> Option<T> {
+    remaining_reads[wire_id.0] -= 1;
+    if remaining_reads[wire_id.0] == 0 {
+        live.remove(&wire_id.0)
+    } else {
+        live.get(&wire_id.0).cloned()
+    }
+}
+
+/// Same as `eval_local`, but the gates of each layer are evaluated in parallel with rayon.
+/// Gates in a layer only read wires of earlier layers, so they don't depend on each other.
+/// Values are cloned on every read instead of being moved at the last one.
+#[cfg(feature = "rayon")]
+pub fn eval_local_parallel<T: Ring>(
+    circuit: &Circuit,
+    input_values: Vec<T>,
+) -> Result<Vec<T>, EvalLocalError> {
+    use rayon::prelude::*;
+
+    check_input_length(circuit, &input_values)?;
+    let gate_layers = circuit.layers().gate_layers.clone();
+    let mut values = constant_values(circuit);
+    for (wire_id, value) in circuit.get_all_inputs().iter().zip(input_values) {
+        values[wire_id.0] = Some(value);
+    }
+
+    let all_gates = circuit.get_all_gates();
+    for layer in gate_layers.iter() {
+        // values are only read while the layer is evaluated, and written back afterwards
+        let outputs: Vec<(usize, Option<T>)> = layer
+            .par_iter()
+            .map(|gate_id| {
+                let gate = &all_gates[*gate_id];
+                let inputs: Option<Vec<T>> = gate
+                    .get_inputs()
+                    .iter()
+                    .map(|input| values[input.0].clone())
+                    .collect();
+                (
+                    gate.get_output().0,
+                    inputs.map(|inputs| gate.evaluate(inputs)),
+                )
+            })
+            .collect();
+
+        for (out, value) in outputs {
+            // a wire driven by several gates keeps the value of the first one
+            if value.is_some() && values[out].is_none() {
+                values[out] = value;
+            }
+        }
     }
 
+    let offset = circuit.get_wire_offset();
+    check_empty_wires(
+        values[offset..]
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| value.is_none())
+            .map(|(i, _)| offset + i)
+            .collect(),
+    )?;
+
     Ok(circuit
         .get_all_outputs()
         .iter()
-        .map(|out| wires.get(out.0).unwrap().value.unwrap())
+        .map(|out| values[out.0].clone().unwrap())
         .collect())
 }
 
+/// Check that there is exactly one value for each input wire of the circuit, see `Circuit::check_inputs`.
+pub(crate) fn check_input_length<T: Ring>(
+    circuit: &Circuit,
+    input_values: &[T],
+) -> Result<(), EvalLocalError> {
+    match circuit.check_inputs(input_values) {
+        Err(CircuitError::InputLengthMismatch { expected, got }) => {
+            Err(EvalLocalError::InputLengthMismatch { expected, got })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Evaluate every gate whose inputs can be computed, instead of failing on the first missing value.
+/// Returns the values of all the evaluated wires, including the input wires,
+/// and an `EvalLocalError::UndrivenOutput` for each output wire which couldn't be evaluated.
+/// If the number of input values doesn't match the number of input wires, the errors start with
+/// `EvalLocalError::InputLengthMismatch`, and the evaluation goes on with the values given for the first input wires.
+/// Mostly used for debugging partially built circuits.
+#[cfg(feature = "std")]
+pub fn eval_local_best_effort<T: Ring>(
+    circuit: &Circuit,
+    input_values: Vec<T>,
+) -> (HashMap<WireId, T>, Vec<EvalLocalError>) {
+    let mut errors = vec![];
+    let expected = circuit.get_all_inputs().len();
+    if input_values.len() != expected {
+        errors.push(EvalLocalError::InputLengthMismatch {
+            expected,
+            got: input_values.len(),
+        });
+    }
+
+    // gates whose input wires don't all get a value are skipped by the evaluation
+    let (values, _) = evaluate_wires(circuit, input_values, true);
+
+    for (i, out) in circuit.get_all_outputs().iter().enumerate() {
+        // report an output marked more than once only once
+        if values[out.0].is_none() && !circuit.get_all_outputs()[..i].contains(out) {
+            errors.push(EvalLocalError::UndrivenOutput { wire_id: out.0 });
+        }
+    }
+
+    let evaluated = values
+        .into_iter()
+        .enumerate()
+        .filter_map(|(wire_id, value)| value.map(|value| (WireId(wire_id), value)))
+        .collect();
+
+    (evaluated, errors)
+}
+
+/// Evaluate every gate whose inputs are all known, starting from the values in `known` and the constant wires.
+/// Returns the state of every wire of the circuit in wire id order: its value, or None if it couldn't be computed.
+/// Unlike `eval_local`, missing values are not an error, so any subset of the inputs can be given.
+///
+/// `known` may also hold values of other wires, e.g. to see what follows from an intermediate value.
+/// A known value is never replaced by the value of the gate driving its wire.
+/// Gates are evaluated layer by layer, see `Circuit::layers`, so gates outside the layering,
+/// which are on a cycle or depend on a wire that is neither an input nor driven by a gate, stay unevaluated.
+#[cfg(feature = "std")]
+pub fn eval_local_partial<T: Ring>(
+    circuit: &Circuit,
+    known: &HashMap<WireId, T>,
+) -> Vec<(WireId, Option<T>)> {
+    let mut values = constant_values::<T>(circuit);
+    for (wire, value) in known.iter() {
+        if let Some(slot) = values.get_mut(wire.0) {
+            *slot = Some(value.clone());
+        }
+    }
+
+    let gates = circuit.get_all_gates();
+    for layer in circuit.layers().gate_layers.iter() {
+        for id in layer {
+            let gate = &gates[*id];
+            let out = gate.get_output().0;
+            if values[out].is_some() {
+                continue;
+            }
+            let inputs: Option<Vec<T>> = gate
+                .get_inputs()
+                .iter()
+                .map(|wire| values[wire.0].clone())
+                .collect();
+            values[out] = inputs.map(|inputs| gate.evaluate(inputs));
+        }
+    }
+
+    values
+        .into_iter()
+        .enumerate()
+        .skip(circuit.get_wire_offset())
+        .map(|(wire_id, value)| (WireId(wire_id), value))
+        .collect()
+}
+
+/// Fails with `EvalLocalError::EmptyWire` if `wire_ids`, the wires left without a value, is not empty.
+pub(crate) fn check_empty_wires(wire_ids: Vec<usize>) -> Result<(), EvalLocalError> {
+    if wire_ids.is_empty() {
+        Ok(())
+    } else {
+        Err(EvalLocalError::EmptyWire { wire_ids })
+    }
+}
+
+/// Evaluate all the gates of a circuit and return the values of wires indexed by wire id.
+/// Second element of returned tuple lists the wires which have not been evaluated, see `check_empty_wires`.
+///
+/// If `keep_values` is false, values are moved out of a wire at its last read instead of being cloned,
+/// so that rings backed by large buffers are not copied more than needed.
+/// In that case only the output wires are guaranteed to still hold their values.
+pub(crate) fn evaluate_wires<T: Ring>(
+    circuit: &Circuit,
+    input_values: Vec<T>,
+    keep_values: bool,
+) -> (Vec<Option<T>>, Vec<usize>) {
+    let layers = circuit.layers();
+    evaluate_layers(
+        circuit,
+        &layers.gate_layers,
+        constant_values(circuit),
+        input_values,
+        keep_values,
+    )
+}
+
+/// Returns the values of the constant wires of ring `T`, indexed by wire id.
+pub(crate) fn constant_values<T: Ring>(circuit: &Circuit) -> Vec<Option<T>> {
+    let mut values = vec![None; circuit.get_wire_count()];
+    for constant in circuit.get_constants() {
+        values[constant.get_wire_id().0] = constant.get_value::<T>().cloned();
+    }
+    values
+}
+
+/// Same as `evaluate_wires`, with the gate layers of the circuit already looked up,
+/// and `constants` as the values of the constant wires, see `constant_values`.
+fn evaluate_layers<T: Ring>(
+    circuit: &Circuit,
+    gate_layers: &[Vec<usize>],
+    constants: Vec<Option<T>>,
+    input_values: Vec<T>,
+    keep_values: bool,
+) -> (Vec<Option<T>>, Vec<usize>) {
+    let all_gates = circuit.get_all_gates();
+    let all_inputs = circuit.get_all_inputs();
+
+    // Output wires, or every wire if `keep_values` is set, are read once more by the caller,
+    // so they are never moved out by gates.
+    let mut remaining_reads = vec![0; circuit.get_wire_count()];
+    for gate in all_gates {
+        for input in gate.get_inputs() {
+            remaining_reads[input.0] += 1;
+        }
+    }
+    if keep_values {
+        for reads in remaining_reads.iter_mut() {
+            *reads += 1;
+        }
+    } else {
+        for out in circuit.get_all_outputs() {
+            remaining_reads[out.0] += 1;
+        }
+    }
+    // whether a value has been put to the wire, even if it was moved out later.
+    let mut values = constants;
+    let mut assigned: Vec<bool> = values.iter().map(|value| value.is_some()).collect();
+
+    // put value to input wires
+    for (wire_id, value) in all_inputs.iter().zip(input_values) {
+        values[wire_id.0] = Some(value);
+        assigned[wire_id.0] = true;
+    }
+
+    for layer in gate_layers.iter() {
+        for gate_id in layer.iter() {
+            let gate = &all_gates[*gate_id];
+            let out = gate.get_output();
+
+            // every input is read, even after a missing one, so that read counts stay in sync
+            let inputs: Vec<Option<T>> = gate
+                .get_inputs()
+                .into_iter()
+                .map(|input| read_wire(&mut values, &mut remaining_reads, input))
+                .collect();
+
+            // a wire driven by several gates keeps the value of the first one
+            if assigned[out.0] {
+                continue;
+            }
+            if let Some(inputs) = inputs.into_iter().collect::<Option<Vec<T>>>() {
+                values[out.0] = Some(gate.evaluate(inputs));
+                assigned[out.0] = true;
+            }
+        }
+    }
+
+    // collect the wires which have not been evaluated
+    let empty = (circuit.get_wire_offset()..assigned.len())
+        .filter(|wire_id| !assigned[*wire_id])
+        .collect();
+
+    (values, empty)
+}
+
+/// Read the value of a wire, moving it out if this is the last read of the wire.
+fn read_wire<T: Ring>(
+    values: &mut [Option<T>],
+    remaining_reads: &mut [usize],
+    wire_id: WireId,
+) -> Option<T> {
+    remaining_reads[wire_id.0] -= 1;
+    if remaining_reads[wire_id.0] == 0 {
+        values[wire_id.0].take()
+    } else {
+        values[wire_id.0].clone()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::eval_local;
-    use crate::{circuit::*, ring::Ring};
-    use ff::PrimeField;
-
-    // Use finite field as a Ring
-    // ff implements similar
-    #[derive(PrimeField)]
-    #[PrimeFieldModulus = "52435875175126190479447740508185965837690552500527637822603658699938581184513"]
-    #[PrimeFieldGenerator = "7"]
-    #[PrimeFieldReprEndianness = "little"]
-    struct Fp([u64; 4]);
-    impl Ring for Fp {}
+    use super::{
+        eval_local, eval_local_batch, eval_local_streaming, eval_local_trace,
+        label_wires_with_layer, EvalLocalError,
+    };
+    #[cfg(feature = "std")]
+    use super::{eval_local_best_effort, eval_local_named, eval_local_partial};
+    use crate::circuit::*;
+    use crate::error::{CircuitError, CircuitResult};
+    use crate::test_utils::{Counted, Fp};
+    #[cfg(feature = "std")]
+    use std::collections::HashMap;
 
     #[test]
     fn test_add_gate() {
@@ -153,9 +627,9 @@ mod tests {
         let out_id = circuit.create_new_wire();
         circuit.add_gate(GateType::Add, x_id, y_id, out_id);
 
-        circuit.mark_input(x_id);
-        circuit.mark_input(y_id);
-        circuit.mark_output(out_id);
+        circuit.mark_input(x_id).unwrap();
+        circuit.mark_input(y_id).unwrap();
+        circuit.mark_output(out_id).unwrap();
         // check circuit is valid
         assert!(circuit.is_valid().is_ok(), "Circuit should be valid");
 
@@ -165,6 +639,85 @@ mod tests {
         assert_eq!(result, Ok(vec![5.into()]), "Circuit: 2 + 3 should output 5");
     }
 
+    #[test]
+    fn test_constant_wire() {
+        let mut circuit = Circuit::new();
+
+        // x * 2 = out, where 2 is a constant wire
+        let x_id = circuit.add_input();
+        let two = circuit.create_constant_wire(Fp::from(2));
+        let out = circuit.add_output_gate(GateType::Mul, x_id, two);
+
+        assert_eq!(circuit.get_all_inputs(), &[x_id]);
+        assert_eq!(circuit.get_constants()[0].get_wire_id(), two);
+
+        let inputs: Vec<Fp> = vec![7.into()];
+        let result = eval_local(&circuit, inputs);
+        assert_eq!(
+            result,
+            Ok(vec![14.into()]),
+            "Circuit: 7 * 2 should output 14"
+        );
+
+        // the constant has no value in another ring
+        let inputs = vec![Counted(7)];
+        assert_eq!(
+            eval_local(&circuit, inputs),
+            Err(EvalLocalError::EmptyWire {
+                wire_ids: vec![two.0, out.0]
+            })
+        );
+    }
+
+    #[test]
+    fn test_input_length_mismatch() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, x, y);
+
+        let too_few: Vec<Fp> = vec![1.into()];
+        assert_eq!(
+            eval_local(&circuit, too_few),
+            Err(EvalLocalError::InputLengthMismatch {
+                expected: 2,
+                got: 1
+            })
+        );
+
+        let too_many: Vec<Fp> = vec![1.into(), 2.into(), 3.into()];
+        assert_eq!(
+            eval_local(&circuit, too_many),
+            Err(EvalLocalError::InputLengthMismatch {
+                expected: 2,
+                got: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_sub_gate() {
+        let mut circuit = Circuit::new();
+
+        // This circuit has a simple sub gate
+        // x - y = out
+        let x_id = circuit.create_new_wire();
+        let y_id = circuit.create_new_wire();
+        let out_id = circuit.create_new_wire();
+        circuit.add_gate(GateType::Sub, x_id, y_id, out_id);
+
+        circuit.mark_input(x_id).unwrap();
+        circuit.mark_input(y_id).unwrap();
+        circuit.mark_output(out_id).unwrap();
+        // check circuit is valid
+        assert!(circuit.is_valid().is_ok(), "Circuit should be valid");
+
+        // inputs: [x_id, y_id]
+        let inputs: Vec<Fp> = vec![5.into(), 3.into()];
+        let result = eval_local(&circuit, inputs);
+        assert_eq!(result, Ok(vec![2.into()]), "Circuit: 5 - 3 should output 2");
+    }
+
     #[test]
     fn test_mul_gate() {
         let mut circuit = Circuit::new();
@@ -176,9 +729,9 @@ mod tests {
         let out_id = circuit.create_new_wire();
         circuit.add_gate(GateType::Mul, x_id, y_id, out_id);
 
-        circuit.mark_input(x_id);
-        circuit.mark_input(y_id);
-        circuit.mark_output(out_id);
+        circuit.mark_input(x_id).unwrap();
+        circuit.mark_input(y_id).unwrap();
+        circuit.mark_output(out_id).unwrap();
         // check circuit is valid
         assert!(circuit.is_valid().is_ok(), "Circuit should be valid");
 
@@ -188,6 +741,120 @@ mod tests {
         assert_eq!(result, Ok(vec![6.into()]), "Circuit: 2 * 3 should output 6");
     }
 
+    #[test]
+    fn test_many_gates_in_reverse_order() {
+        let mut circuit = Circuit::new();
+
+        // out = in + in + ... + in, with gates added from the last one to the first one,
+        // which is the worst order for scanning gates until everything is labeled.
+        let gate_count = 5000;
+        let input = circuit.add_input();
+        let wires: Vec<WireId> = (0..gate_count).map(|_| circuit.create_new_wire()).collect();
+        for i in (0..gate_count).rev() {
+            let prev = if i == 0 { input } else { wires[i - 1] };
+            circuit.add_gate(GateType::Add, prev, input, wires[i]);
+        }
+        circuit.mark_output(wires[gate_count - 1]).unwrap();
+
+        let result = eval_local(&circuit, vec![Fp::from(1)]);
+        assert_eq!(result, Ok(vec![Fp::from(gate_count as u64 + 1)]));
+    }
+
+    #[test]
+    fn test_cyclic_circuit_fails() {
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, input, b, a);
+        circuit.add_gate(GateType::Mul, input, a, b);
+        circuit.mark_output(b).unwrap();
+
+        let result = eval_local(&circuit, vec![Fp::from(1)]);
+        assert_eq!(
+            result,
+            Err(EvalLocalError::EmptyWire {
+                wire_ids: vec![a.0, b.0]
+            })
+        );
+    }
+
+    #[test]
+    fn test_disconnected_wire_is_reported() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        // neither an input nor driven by a gate
+        let dangling = circuit.create_new_wire();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, dangling, sum);
+        circuit.add_output_gate(GateType::Mul, x, x);
+
+        let result = eval_local(&circuit, vec![Fp::from(3)]);
+        assert_eq!(
+            result,
+            Err(EvalLocalError::EmptyWire {
+                wire_ids: vec![dangling.0, sum.0]
+            })
+        );
+    }
+
+    #[test]
+    fn test_errors_propagate_as_circuit_error() {
+        fn build_and_evaluate(connect: bool) -> CircuitResult<Vec<Fp>> {
+            let mut circuit = Circuit::new();
+            let x = circuit.add_input();
+            let y = if connect {
+                circuit.add_input()
+            } else {
+                circuit.create_new_wire()
+            };
+            let sum = circuit.create_new_wire();
+            circuit.add_gate(GateType::Add, x, y, sum);
+            circuit.mark_output(sum)?;
+            let inputs = vec![Fp::from(3); circuit.input_arity()];
+            Ok(eval_local(&circuit, inputs)?)
+        }
+
+        assert_eq!(build_and_evaluate(true), Ok(vec![Fp::from(6)]));
+        assert_eq!(
+            build_and_evaluate(false),
+            Err(CircuitError::EvaluationFailed(EvalLocalError::EmptyWire {
+                wire_ids: vec![1, 2]
+            }))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_partial_inputs() {
+        // sum = x + y
+        // out = x * 2
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let two = circuit.create_constant_wire(Fp::from(2));
+        let sum = circuit.add_output_gate(GateType::Add, x, y);
+        let out = circuit.add_output_gate(GateType::Mul, x, two);
+
+        let known = HashMap::from([(x, Fp::from(3))]);
+        assert_eq!(
+            eval_local_partial(&circuit, &known),
+            vec![
+                (x, Some(Fp::from(3))),
+                (y, None),
+                (two, Some(Fp::from(2))),
+                (sum, None),
+                (out, Some(Fp::from(6))),
+            ]
+        );
+
+        // an intermediate value is kept as given
+        let known = HashMap::from([(y, Fp::from(4)), (sum, Fp::from(10))]);
+        let states = eval_local_partial(&circuit, &known);
+        assert_eq!(states[sum.0], (sum, Some(Fp::from(10))));
+        assert_eq!(states[out.0], (out, None));
+    }
+
     #[test]
     fn test_multiple_outputs() {
         let mut circuit = Circuit::new();
@@ -207,11 +874,11 @@ mod tests {
         let out2 = circuit.create_new_wire();
         circuit.add_gate(GateType::Mul, in3, out1, out2);
 
-        circuit.mark_input(in1);
-        circuit.mark_input(in2);
-        circuit.mark_input(in3);
-        circuit.mark_output(out1);
-        circuit.mark_output(out2);
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
 
         let inputs: Vec<Fp> = vec![1.into(), 2.into(), 3.into()];
         let result = eval_local(&circuit, inputs);
@@ -221,4 +888,359 @@ mod tests {
             "Circuit output2 two values: [3, 9]"
         );
     }
+
+    #[test]
+    fn test_streaming_matches_eval_local() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        // out3 = out2 - 5, with out1 marked as an output again
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let in3 = circuit.add_input();
+        let out1 = circuit.add_output_gate(GateType::Add, in1, in2);
+        let out2 = circuit.add_output_gate(GateType::Mul, in3, out1);
+        let five = circuit.create_constant_wire(Fp::from(5));
+        circuit.add_output_gate(GateType::Sub, out2, five);
+        circuit.mark_output(out1).unwrap();
+
+        let inputs: Vec<Fp> = vec![1.into(), 2.into(), 3.into()];
+        let expected = eval_local(&circuit, inputs.clone());
+        assert_eq!(expected, Ok(vec![3.into(), 9.into(), 4.into(), 3.into()]));
+        assert_eq!(eval_local_streaming(&circuit, inputs), expected);
+
+        // gates added out of order are still evaluated after their inputs
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_output_gate(GateType::Mul, sum, x);
+        circuit.add_gate(GateType::Add, x, x, sum);
+        assert_eq!(
+            eval_local_streaming(&circuit, vec![Fp::from(3)]),
+            Ok(vec![Fp::from(18)])
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_parallel_matches_sequential() {
+        let mut circuit = Circuit::new();
+
+        // a single layer of 1000 multiplications: out_i = in_i * y
+        let y = circuit.add_input();
+        let inputs: Vec<WireId> = (0..1000).map(|_| circuit.add_input()).collect();
+        for input in inputs.iter() {
+            circuit.add_output_gate(GateType::Mul, *input, y);
+        }
+
+        let values: Vec<Fp> = (0..1001).map(|i| Fp::from(i * 7 + 3)).collect();
+        assert_eq!(
+            super::eval_local_parallel(&circuit, values.clone()),
+            eval_local(&circuit, values)
+        );
+    }
+
+    #[test]
+    fn test_trace_has_intermediate_values() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        let inputs: Vec<Fp> = vec![1.into(), 2.into(), 3.into()];
+        let values = eval_local_trace(&circuit, inputs).unwrap();
+        assert_eq!(values[out1.0], Some(3.into()));
+        assert_eq!(values[out2.0], Some(9.into()));
+    }
+
+    #[test]
+    fn test_add_many() {
+        let mut circuit = Circuit::new();
+
+        // out = (in1 + in2 + in3 + in4 + in5) * in1
+        let inputs: Vec<WireId> = (0..5).map(|_| circuit.add_input()).collect();
+        let sum = circuit.add_many(&inputs);
+        circuit.add_output_gate(GateType::Mul, sum, inputs[0]);
+
+        assert_eq!(circuit.get_gate_count(), 2);
+        assert_eq!(circuit.get_all_gates()[0].arity(), 5);
+        assert_eq!(
+            label_wires_with_layer(&circuit).gate_layers,
+            vec![vec![0], vec![1]]
+        );
+        assert_eq!(circuit.is_valid(), Ok(()));
+
+        let values: Vec<Fp> = (1..=5).map(Fp::from).collect();
+        assert_eq!(eval_local(&circuit, values), Ok(vec![Fp::from(15)]));
+    }
+
+    #[test]
+    fn test_neg() {
+        let mut circuit = Circuit::new();
+
+        // out = -in + in
+        let input = circuit.add_input();
+        let neg = circuit.add_neg(input);
+        circuit.mark_output(neg).unwrap();
+        circuit.add_output_gate(GateType::Add, neg, input);
+
+        assert_eq!(circuit.get_all_gates()[0].get_inputs(), vec![input]);
+        assert_eq!(circuit.is_valid(), Ok(()));
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(4)]),
+            Ok(vec![-Fp::from(4), Fp::from(0)])
+        );
+    }
+
+    #[test]
+    fn test_scalar_mul() {
+        let mut circuit = Circuit::new();
+
+        // out = 7 * in
+        let input = circuit.add_input();
+        let out = circuit.add_scalar_mul(input, Fp::from(7));
+        circuit.mark_output(out).unwrap();
+
+        assert_eq!(circuit.get_all_gates()[0].gate_type(), GateType::ScalarMul);
+        assert_eq!(circuit.get_constants().len(), 1);
+        assert_eq!(circuit.is_valid(), Ok(()));
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(6)]),
+            Ok(vec![Fp::from(42)])
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_named_inputs_and_outputs() {
+        let mut circuit = Circuit::new();
+
+        // sum = x + y
+        // product = x * y
+        let x = circuit.create_new_wire();
+        let y = circuit.create_new_wire();
+        circuit.mark_input_named(x, "x").unwrap();
+        circuit.mark_input_named(y, "y").unwrap();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        circuit.mark_output_named(sum, "sum").unwrap();
+        circuit.add_output_gate(GateType::Mul, x, y);
+
+        assert_eq!(circuit.get_input_by_name("x"), Some(x));
+        assert_eq!(circuit.get_input_by_name("y"), Some(y));
+        assert_eq!(circuit.get_input_by_name("z"), None);
+        assert_eq!(circuit.get_output_by_name("sum"), Some(sum));
+
+        let values = eval_local_named(&circuit, vec![Fp::from(2), Fp::from(5)]).unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["sum"], Fp::from(7));
+        // positional evaluation is unchanged
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(2), Fp::from(5)]),
+            Ok(vec![Fp::from(7), Fp::from(10)])
+        );
+    }
+
+    #[test]
+    fn test_batch_matches_single_evaluation() {
+        let mut circuit = Circuit::new();
+
+        // out1 = x * y + 3
+        // out2 = -(x - y)
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let three = circuit.create_constant_wire(Fp::from(3));
+        let product = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, x, y, product);
+        circuit.add_output_gate(GateType::Add, product, three);
+        let diff = circuit.create_new_wire();
+        circuit.add_gate(GateType::Sub, x, y, diff);
+        let neg = circuit.add_neg(diff);
+        circuit.mark_output(neg).unwrap();
+
+        let batch: Vec<Vec<Fp>> = (0..100u64)
+            .map(|i| vec![Fp::from(i), Fp::from(i * i + 1)])
+            .collect();
+        let outputs = eval_local_batch(&circuit, &batch).unwrap();
+
+        assert_eq!(outputs.len(), 100);
+        for (inputs, outputs) in batch.into_iter().zip(outputs) {
+            assert_eq!(eval_local(&circuit, inputs), Ok(outputs));
+        }
+    }
+
+    #[test]
+    fn test_batch_rejects_wrong_length() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, x, y);
+
+        let batch = vec![vec![Fp::from(1), Fp::from(2)], vec![Fp::from(1)]];
+        assert_eq!(
+            eval_local_batch(&circuit, &batch),
+            Err(EvalLocalError::InputLengthMismatch {
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_layers_are_cached() {
+        let mut circuit = Circuit::new();
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, in1, in2);
+
+        let inputs = vec![Fp::from(2), Fp::from(3)];
+        assert_eq!(eval_local(&circuit, inputs.clone()), Ok(vec![Fp::from(5)]));
+        assert_eq!(eval_local(&circuit, inputs.clone()), Ok(vec![Fp::from(5)]));
+        assert_eq!(circuit.layer_computations.get(), 1);
+
+        // adding a gate clears the cache
+        let out = circuit.add_output_gate(GateType::Mul, in1, in2);
+        assert_eq!(
+            eval_local(&circuit, inputs),
+            Ok(vec![Fp::from(5), Fp::from(6)])
+        );
+        assert_eq!(circuit.layer_computations.get(), 2);
+        assert_eq!(circuit.layers().wire_layers[out.0], Some(1));
+    }
+
+    #[test]
+    fn test_last_use_moves_values() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        // out1 is read by gate2 and is also an output, so it has to be cloned once.
+        // Every other value is moved at its last read.
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        Counted::reset();
+        let inputs = vec![Counted(1), Counted(2), Counted(3)];
+        let result = eval_local(&circuit, inputs);
+        assert_eq!(result, Ok(vec![Counted(3), Counted(9)]));
+        assert_eq!(Counted::clones(), 1, "Only out1 should be cloned");
+    }
+
+    #[test]
+    fn test_convenience_constructors() {
+        let mut circuit = Circuit::new();
+
+        // out = (x + y) * z
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let z = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        circuit.add_output_gate(GateType::Mul, sum, z);
+
+        assert_eq!(circuit.get_all_inputs(), &[x, y, z]);
+        assert!(circuit.is_valid().is_ok(), "Circuit should be valid");
+
+        let inputs: Vec<Fp> = vec![1.into(), 2.into(), 3.into()];
+        let result = eval_local(&circuit, inputs);
+        assert_eq!(
+            result,
+            Ok(vec![9.into()]),
+            "Circuit: (1 + 2) * 3 should output 9"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_best_effort_reports_all_undriven_outputs() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = in1 * floating1
+        // out3 = floating2 + in2
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let floating1 = circuit.create_new_wire();
+        let floating2 = circuit.create_new_wire();
+        let out1 = circuit.add_output_gate(GateType::Add, in1, in2);
+        let out2 = circuit.add_output_gate(GateType::Mul, in1, floating1);
+        let out3 = circuit.add_output_gate(GateType::Add, floating2, in2);
+
+        let inputs: Vec<Fp> = vec![1.into(), 2.into()];
+        let (values, errors) = eval_local_best_effort(&circuit, inputs);
+        assert_eq!(values.get(&out1), Some(&3.into()));
+        assert_eq!(values.len(), 3, "Only inputs and out1 should be evaluated");
+        assert_eq!(
+            errors,
+            vec![
+                EvalLocalError::UndrivenOutput { wire_id: out2.0 },
+                EvalLocalError::UndrivenOutput { wire_id: out3.0 },
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_best_effort_reports_input_length_mismatch() {
+        let mut circuit = Circuit::new();
+
+        // out = in1 + in2
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let out = circuit.add_output_gate(GateType::Add, in1, in2);
+
+        let inputs: Vec<Fp> = vec![1.into(), 2.into(), 3.into()];
+        let (values, errors) = eval_local_best_effort(&circuit, inputs);
+        assert_eq!(values.get(&out), Some(&3.into()));
+        assert_eq!(
+            errors,
+            vec![EvalLocalError::InputLengthMismatch {
+                expected: 2,
+                got: 3
+            }]
+        );
+
+        let inputs: Vec<Fp> = vec![1.into()];
+        let (_, errors) = eval_local_best_effort(&circuit, inputs);
+        assert_eq!(
+            errors,
+            vec![
+                EvalLocalError::InputLengthMismatch {
+                    expected: 2,
+                    got: 1
+                },
+                EvalLocalError::UndrivenOutput { wire_id: out.0 },
+            ]
+        );
+    }
 }
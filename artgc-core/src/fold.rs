@@ -0,0 +1,155 @@
+//! Constant folding: gates whose input values are known before evaluation are computed once
+//! and removed, so they don't have to be garbled.
+
+use crate::circuit::{Circuit, WireId};
+use crate::ring::Ring;
+use std::collections::HashMap;
+
+impl Circuit {
+    /// Evaluate every gate whose input wires are all known, and remove it from the circuit.
+    /// Known wires are the constant wires of ring `T`, the wires in `constants`
+    /// and the output wires of gates folded before.
+    /// Entries of `constants` for output wires of gates are ignored.
+    ///
+    /// The output wire of a folded gate becomes a constant wire, so the output wires of the circuit
+    /// and the gates reading it are unchanged. So does a wire in `constants` which is not an input.
+    /// Remaining gates are renumbered in their original order.
+    ///
+    /// Wires are never removed, so wires which are no longer read are left behind,
+    /// and the circuit may not pass `is_valid` afterwards. It can still be evaluated.
+    pub fn fold_constants<T: Ring>(&mut self, constants: &HashMap<WireId, T>) {
+        let wire_count = self.get_wire_count();
+        let gates = self.get_all_gates();
+
+        let mut is_constant = vec![false; wire_count];
+        let mut values: Vec<Option<T>> = vec![None; wire_count];
+        for constant in self.get_constants() {
+            let wire = constant.get_wire_id().0;
+            is_constant[wire] = true;
+            values[wire] = constant.get_value::<T>().cloned();
+        }
+
+        let mut is_driven = vec![false; wire_count];
+        for gate in gates.iter() {
+            is_driven[gate.get_output().0] = true;
+        }
+        for (wire, value) in constants.iter() {
+            if wire.0 < wire_count && !is_driven[wire.0] && values[wire.0].is_none() {
+                values[wire.0] = Some(value.clone());
+            }
+        }
+
+        // propagate known values forward from the known wires
+        let connections = self.wire_connections();
+        let mut folded = vec![false; gates.len()];
+        let mut stack: Vec<usize> = (0..wire_count).filter(|w| values[*w].is_some()).collect();
+        while let Some(wire) = stack.pop() {
+            for gate_id in connections[wire].to_ids.iter() {
+                let gate = &gates[*gate_id];
+                let out = gate.get_output().0;
+                // a wire driven by several gates only takes the value of the first folded one
+                if folded[*gate_id] || values[out].is_some() {
+                    continue;
+                }
+                let inputs: Option<Vec<T>> = gate
+                    .get_inputs()
+                    .iter()
+                    .map(|input| values[input.0].clone())
+                    .collect();
+                let Some(inputs) = inputs else {
+                    continue;
+                };
+                values[out] = Some(gate.evaluate(inputs));
+                folded[*gate_id] = true;
+                stack.push(out);
+            }
+        }
+
+        // known wires which are not inputs become constant wires,
+        // so that every wire still has a value on evaluation
+        let keep: Vec<bool> = folded.iter().map(|folded| !folded).collect();
+        let mut is_fixed = is_constant;
+        for (gate, _) in gates.iter().zip(&keep).filter(|(_, keep)| **keep) {
+            is_fixed[gate.get_output().0] = true;
+        }
+        for input in self.get_all_inputs() {
+            is_fixed[input.0] = true;
+        }
+
+        let bound: Vec<(WireId, T)> = values
+            .into_iter()
+            .enumerate()
+            .filter(|(w, _)| !is_fixed[*w])
+            .filter_map(|(w, value)| Some((WireId(w), value?)))
+            .collect();
+
+        self.retain_gates(&keep);
+        for (wire, value) in bound {
+            self.bind_constant(wire, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::*;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+    use std::collections::HashMap;
+
+    #[test]
+    fn fold_sum_of_constants() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out = (2 + 3) * x
+        let two = circuit.create_new_wire();
+        let three = circuit.create_new_wire();
+        let x = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, two, three, sum);
+        let out = circuit.add_output_gate(GateType::Mul, sum, x);
+
+        let constants = HashMap::from([(two, Fp::from(2)), (three, Fp::from(3))]);
+        circuit.fold_constants(&constants);
+
+        // out = 5 * x
+        assert_eq!(circuit.get_gate_count(), 1);
+        let gate = &circuit.get_all_gates()[0];
+        assert_eq!(gate.gate_type(), GateType::Mul);
+        assert_eq!(gate.get_inputs(), vec![sum, x]);
+        assert_eq!(circuit.get_all_outputs(), &[out]);
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(7)]),
+            Ok(vec![Fp::from(35)])
+        );
+    }
+
+    #[test]
+    fn fold_chain_of_constant_wires() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // a = 2 * 3
+        // b = a - 1
+        // out = b + x
+        let x = circuit.add_input();
+        let two = circuit.create_constant_wire(Fp::from(2));
+        let three = circuit.create_constant_wire(Fp::from(3));
+        let one = circuit.create_constant_wire(Fp::from(1));
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, two, three, a);
+        circuit.add_gate(GateType::Sub, a, one, b);
+        circuit.add_output_gate(GateType::Add, b, x);
+
+        circuit.fold_constants::<Fp>(&HashMap::new());
+
+        assert_eq!(circuit.get_gate_count(), 1);
+        assert_eq!(circuit.get_all_gates()[0].get_inputs(), vec![b, x]);
+        assert_eq!(
+            eval_local(&circuit, vec![Fp::from(10)]),
+            Ok(vec![Fp::from(15)])
+        );
+    }
+}
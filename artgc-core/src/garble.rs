@@ -0,0 +1,747 @@
+//! Garbling of arithmetic circuits.
+//!
+//! **This scheme is not secure.** It is a first cut to get the garbler and evaluator roles in place,
+//! and it hides nothing from the evaluator.
+//!
+//! Every wire gets a random `Key`, and values are encoded into labels with it, see `label`.
+//! Constant wires are public, so their labels are their values.
+//! Both components of the labels are garbled independently, with the same tables for each component.
+//! For each gate, the garbler publishes a table which lets the evaluator turn a component of the labels
+//! of its input wires into that component of the label of its output wire, without knowing any value.
+//! With `r` the mask of a wire for that component and `L` its label component:
+//! - Add, Sub, AddMany and Neg gates are linear, so the output label is the same operation on the input labels,
+//!   plus an offset correcting the masks.
+//! - Mul gates publish the masks of both inputs, since
+//!   `x * y + r_out = L_x * L_y - r_y * L_x - r_x * L_y + (r_x * r_y + r_out)`.
+//!   ScalarMul gates are garbled the same way, with the zero mask of their scalar.
+//!
+//! The masks of Mul inputs are published as they are, so the evaluator can decode those wires.
+//!
+//! With `GarbleOptions::half_gates`, Mul gates drop the offset from their table, see `garble_mul_halfgate`.
+//! With `GarbleOptions::free_addition`, linear gates have no table at all: the key of their output wire is
+//! `label::linear_key` of their input keys, so the evaluator only applies the gate to the labels.
+//!
+//! Both options trade independence of the keys for smaller tables. A derived output mask is a fixed function of
+//! the input masks, so a party learning some of them learns the others, e.g. `r_out - r_x` is `r_y`
+//! below an Add gate. Random keys limit what a leaked key says about the rest of the circuit, and derived keys don't.
+
+use crate::circuit::{Circuit, Gate, WireId};
+use crate::error::CircuitError;
+use crate::eval_local::EvalLocalError;
+use crate::label::{decode, encode, linear_key, Key, Label};
+use crate::ring::Ring;
+use crate::topo::topological_sort;
+use ff::PrimeField;
+use rand_core::{impls, Error, RngCore};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+/// Public values of a garbled gate, combined by the evaluator with a component of the labels of the input wires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GarbledTable<T> {
+    /// Output label is the gate applied to the input labels, plus `offset`.
+    Linear { offset: T },
+    /// Output label is `L_x * L_y - y_mask * L_x - x_mask * L_y + offset`.
+    Mul { x_mask: T, y_mask: T, offset: T },
+    /// Output label is `L_x * L_y - y_mask * L_x - x_mask * L_y`, see `garble_mul_halfgate`.
+    HalfMul { x_mask: T, y_mask: T },
+    /// Output label is the gate applied to the input labels, see `GarbleOptions::free_addition`.
+    Free,
+}
+
+impl<T: Ring> GarbledTable<T> {
+    /// Returns the table of `gate` for the component whose mask of wire `w` is `mask(w)`.
+    fn new(gate: &Gate, mask: impl Fn(WireId) -> T) -> Self {
+        let r_out = mask(gate.get_output());
+        match gate {
+            Gate::Mul { x, y, .. } | Gate::ScalarMul { x, scalar: y, .. } => {
+                let (x_mask, y_mask) = (mask(*x), mask(*y));
+                GarbledTable::Mul {
+                    offset: x_mask.clone() * &y_mask + &r_out,
+                    x_mask,
+                    y_mask,
+                }
+            }
+            _ => {
+                let input_masks = gate.get_inputs().into_iter().map(mask);
+                GarbledTable::Linear {
+                    offset: r_out - gate.evaluate(input_masks),
+                }
+            }
+        }
+    }
+
+    /// Returns the output label component of `gate` from its input label components.
+    fn evaluate(&self, gate: &Gate, inputs: Vec<T>) -> T {
+        match self {
+            GarbledTable::Linear { offset } => gate.evaluate(inputs) + offset,
+            GarbledTable::Mul {
+                x_mask,
+                y_mask,
+                offset,
+            } => {
+                let (l_x, l_y) = (&inputs[0], &inputs[1]);
+                l_x.clone() * l_y - y_mask.clone() * l_x - x_mask.clone() * l_y + offset
+            }
+            GarbledTable::HalfMul { x_mask, y_mask } => {
+                let (l_x, l_y) = (&inputs[0], &inputs[1]);
+                l_x.clone() * l_y - y_mask.clone() * l_x - x_mask.clone() * l_y
+            }
+            GarbledTable::Free => gate.evaluate(inputs),
+        }
+    }
+
+    /// Returns true if this table can be evaluated for `gate`:
+    /// Mul and ScalarMul gates need a `Mul` or `HalfMul` table, and the other gates a `Linear` or `Free` one.
+    fn fits(&self, gate: &Gate) -> bool {
+        let is_mul = matches!(gate, Gate::Mul { .. } | Gate::ScalarMul { .. });
+        match self {
+            GarbledTable::Mul { .. } | GarbledTable::HalfMul { .. } => is_mul,
+            GarbledTable::Linear { .. } | GarbledTable::Free => !is_mul,
+        }
+    }
+
+    /// Number of ring elements the garbler sends for this table.
+    pub fn size(&self) -> usize {
+        match self {
+            GarbledTable::Linear { .. } => 1,
+            GarbledTable::Mul { .. } => 3,
+            GarbledTable::HalfMul { .. } => 2,
+            GarbledTable::Free => 0,
+        }
+    }
+}
+
+/// Returns the table of a Mul gate whose input masks are `x_mask` and `y_mask`, and the mask of its output wire,
+/// or None if the gate has to be garbled with a full table.
+///
+/// Labels are the value plus the mask, so `L_x * L_y - r_y * L_x - r_x * L_y = x * y - r_x * r_y`
+/// is already the label of `x * y` for the output mask `-r_x * r_y`. Choosing that mask instead of a random one
+/// removes the offset from the table. It relies on the output mask of a Mul gate not being used anywhere else,
+/// so the gate must be garbled after the gates producing its inputs, and before the gates reading its output.
+/// If either input is public, the derived mask is zero and would publish the output, so a full table is needed.
+fn garble_mul_halfgate<T: Ring>(x_mask: &T, y_mask: &T) -> Option<(GarbledTable<T>, T)> {
+    let r_out = T::zero() - x_mask.clone() * y_mask;
+    (r_out != T::zero()).then(|| {
+        let table = GarbledTable::HalfMul {
+            x_mask: x_mask.clone(),
+            y_mask: y_mask.clone(),
+        };
+        (table, r_out)
+    })
+}
+
+/// Output of the garbler: the tables of each gate, one for each label component, indexed by gate id,
+/// and the keys needed to encode the inputs and decode the outputs.
+///
+/// Input keys are not serialized, since they would let the evaluator decode the inputs of the garbler.
+/// A deserialized garbled circuit can be evaluated and decoded, but it can't encode inputs.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GarbledCircuit<T> {
+    tables: Vec<[GarbledTable<T>; 2]>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    input_keys: Vec<Key<T>>,
+    output_keys: Vec<Key<T>>,
+}
+
+impl<T: Ring> GarbledCircuit<T> {
+    pub fn get_tables(&self) -> &[[GarbledTable<T>; 2]] {
+        &self.tables
+    }
+
+    /// Returns true if this garbled circuit has the shape of a garbling of `circuit`:
+    /// one table of the right kind for each gate, and one key for each output.
+    /// Evaluating a garbled circuit received from another party panics unless this holds.
+    pub fn fits(&self, circuit: &Circuit) -> bool {
+        self.tables.len() == circuit.get_gate_count()
+            && self.output_keys.len() == circuit.get_all_outputs().len()
+            && self
+                .tables
+                .iter()
+                .zip(circuit.gate_stream())
+                .all(|(tables, gate)| tables.iter().all(|table| table.fits(gate)))
+    }
+
+    /// Returns the labels of the input wires carrying `values`, in input order.
+    pub fn encode_inputs(&self, values: Vec<T>) -> Result<Vec<Label<T>>, EvalLocalError> {
+        if values.len() != self.input_keys.len() {
+            return Err(EvalLocalError::InputLengthMismatch {
+                expected: self.input_keys.len(),
+                got: values.len(),
+            });
+        }
+        Ok(values
+            .into_iter()
+            .zip(self.input_keys.iter())
+            .map(|(value, key)| encode(value, key))
+            .collect())
+    }
+
+    /// Returns the values of the output labels returned by `Evaluator::evaluate`, in output order.
+    /// Returns None if any of them is not a valid label of its output wire.
+    pub fn decode_outputs(&self, labels: Vec<Label<T>>) -> Option<Vec<T>> {
+        labels
+            .into_iter()
+            .zip(self.output_keys.iter())
+            .map(|(label, key)| decode(label, key))
+            .collect()
+    }
+
+    /// Returns the label of the input wire at `position` in input order carrying `value`.
+    ///
+    /// Panics if there is no input at `position`.
+    pub fn encode_input(&self, position: usize, value: T) -> Label<T> {
+        encode(value, &self.input_keys[position])
+    }
+}
+
+impl<T: Ring + PrimeField> GarbledCircuit<T> {
+    /// Returns the label pairs to offer by oblivious transfer for the inputs at `positions` in input order,
+    /// so that the evaluator can get the labels of its inputs without revealing them.
+    ///
+    /// Choices are bits, so each input gets `T::NUM_BITS` pairs. The pair of bit `i` holds the labels
+    /// of 0 and 2^i under a share of the key of the input, and the shares of an input add up to its key.
+    /// Labels chosen by `input_choices` add up to the label of the input value, see `combine_input_labels`.
+    pub fn input_label_pairs(
+        &self,
+        positions: Range<usize>,
+        rng: &mut impl RngCore,
+    ) -> Vec<(Label<T>, Label<T>)> {
+        let mut pairs = vec![];
+        for key in self.input_keys[positions].iter() {
+            let mut remaining = key.clone();
+            let mut power = T::ONE;
+            for bit in 0..T::NUM_BITS {
+                let share = if bit + 1 == T::NUM_BITS {
+                    remaining.clone()
+                } else {
+                    let share = Key([T::random(&mut *rng), T::random(&mut *rng)]);
+                    remaining = Key([remaining.0[0] - share.0[0], remaining.0[1] - share.0[1]]);
+                    share
+                };
+                pairs.push((encode(T::ZERO, &share), encode(power, &share)));
+                power = power.double();
+            }
+        }
+        pairs
+    }
+}
+
+/// Returns the choices for the pairs of `GarbledCircuit::input_label_pairs`: the bits of each value,
+/// from the least significant one.
+pub fn input_choices<T: PrimeField>(values: &[T]) -> Vec<bool> {
+    let mut choices = vec![];
+    for value in values {
+        let mut value = *value;
+        for _ in 0..T::NUM_BITS {
+            let bit = bool::from(value.is_odd());
+            if bit {
+                value -= T::ONE;
+            }
+            value *= T::TWO_INV;
+            choices.push(bit);
+        }
+    }
+    choices
+}
+
+/// Add up the labels chosen by `input_choices` into one label for each input.
+pub fn combine_input_labels<T: Ring + PrimeField>(labels: Vec<Label<T>>) -> Vec<Label<T>> {
+    labels
+        .chunks(T::NUM_BITS as usize)
+        .map(|bits| {
+            Label(core::array::from_fn(|i| {
+                bits.iter().map(|label| label.0[i]).sum()
+            }))
+        })
+        .collect()
+}
+
+/// Optimizations of the garbled tables. The default garbles every gate with a full table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GarbleOptions {
+    /// Garble Mul gates with 2 ring elements instead of 3, see `garble_mul_halfgate`.
+    pub half_gates: bool,
+    /// Garble Add, Sub, AddMany and Neg gates without any table, see `label::linear_key`.
+    /// A gate whose derived output key would be public keeps its table.
+    pub free_addition: bool,
+}
+
+/// Garbles circuits with keys derived from a seed, so that garbling the same circuit twice gives the same result.
+pub struct Garbler {
+    seed: u64,
+    options: GarbleOptions,
+}
+
+impl Garbler {
+    pub fn new(seed: u64) -> Self {
+        Garbler::with_options(seed, GarbleOptions::default())
+    }
+
+    pub fn with_options(seed: u64, options: GarbleOptions) -> Self {
+        Garbler { seed, options }
+    }
+
+    pub fn garble<T: Ring + PrimeField>(&self, circuit: &Circuit) -> GarbledCircuit<T> {
+        let mut prg = Prg::new(self.seed);
+        let mut keys: Vec<Key<T>> = (0..circuit.get_wire_count())
+            .map(|_| Key([T::random(&mut prg), T::random(&mut prg)]))
+            .collect();
+        for constant in circuit.get_constants() {
+            keys[constant.get_wire_id().0] = Key::public();
+        }
+
+        let tables = if self.options.half_gates || self.options.free_addition {
+            // output keys may be derived from input keys, so inputs are garbled first.
+            // A cyclic circuit can't be evaluated anyway, so it is garbled in id order.
+            let order = topological_sort(circuit)
+                .unwrap_or_else(|_| (0..circuit.get_gate_count()).collect());
+            let gates = circuit.get_all_gates();
+            let mut tables: Vec<Option<[GarbledTable<T>; 2]>> = vec![None; gates.len()];
+            for id in order {
+                tables[id] = Some(self.garble_gate(&gates[id], &mut keys));
+            }
+            tables.into_iter().map(Option::unwrap).collect()
+        } else {
+            circuit
+                .gate_stream()
+                .map(|gate| core::array::from_fn(|i| GarbledTable::new(gate, |w| keys[w.0].0[i])))
+                .collect()
+        };
+
+        GarbledCircuit {
+            tables,
+            input_keys: circuit
+                .get_all_inputs()
+                .iter()
+                .map(|w| keys[w.0].clone())
+                .collect(),
+            output_keys: circuit
+                .get_all_outputs()
+                .iter()
+                .map(|w| keys[w.0].clone())
+                .collect(),
+        }
+    }
+
+    /// Returns the tables of `gate` according to the options, replacing the key of its output wire
+    /// if it is derived from the keys of its input wires.
+    fn garble_gate<T: Ring + PrimeField>(
+        &self,
+        gate: &Gate,
+        keys: &mut [Key<T>],
+    ) -> [GarbledTable<T>; 2] {
+        let out = gate.get_output();
+        match gate {
+            Gate::Mul { x, y, .. } if self.options.half_gates => {
+                return core::array::from_fn(|i| {
+                    match garble_mul_halfgate(&keys[x.0].0[i], &keys[y.0].0[i]) {
+                        Some((table, r_out)) => {
+                            keys[out.0].0[i] = r_out;
+                            table
+                        }
+                        None => GarbledTable::new(gate, |w| keys[w.0].0[i]),
+                    }
+                });
+            }
+            Gate::Add { .. } | Gate::Sub { .. } | Gate::AddMany { .. } | Gate::Neg { .. }
+                if self.options.free_addition =>
+            {
+                let input_keys: Vec<Key<T>> = gate
+                    .get_inputs()
+                    .iter()
+                    .map(|w| keys[w.0].clone())
+                    .collect();
+                let key = linear_key(gate, &input_keys);
+                // a zero mask would publish the output value
+                if key.0.iter().all(|mask| *mask != T::zero()) {
+                    keys[out.0] = key;
+                    return [GarbledTable::Free, GarbledTable::Free];
+                }
+            }
+            _ => {}
+        }
+        core::array::from_fn(|i| GarbledTable::new(gate, |w| keys[w.0].0[i]))
+    }
+}
+
+/// Evaluates garbled circuits of `circuit`, which both parties have to agree on.
+pub struct Evaluator<'a> {
+    circuit: &'a Circuit,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(circuit: &'a Circuit) -> Self {
+        Evaluator { circuit }
+    }
+
+    /// Returns the labels of the output wires, in output order.
+    /// Gates are evaluated in topological order, so a cyclic circuit fails with `EvalLocalError::EmptyWire`
+    /// listing a wire of the cycle. A circuit whose outputs can't be computed from the inputs fails with
+    /// the list of those output wires.
+    pub fn evaluate<T: Ring>(
+        &self,
+        gc: &GarbledCircuit<T>,
+        input_labels: Vec<Label<T>>,
+    ) -> Result<Vec<Label<T>>, EvalLocalError> {
+        let circuit = self.circuit;
+        if input_labels.len() != circuit.get_all_inputs().len() {
+            return Err(EvalLocalError::InputLengthMismatch {
+                expected: circuit.get_all_inputs().len(),
+                got: input_labels.len(),
+            });
+        }
+        let order = topological_sort(circuit).map_err(|e| EvalLocalError::EmptyWire {
+            wire_ids: match e {
+                CircuitError::CyclicPath { wire_id, .. } => vec![wire_id],
+                _ => vec![],
+            },
+        })?;
+
+        let mut labels: Vec<Option<Label<T>>> = vec![None; circuit.get_wire_count()];
+        for constant in circuit.get_constants() {
+            labels[constant.get_wire_id().0] = constant
+                .get_value::<T>()
+                .map(|value| encode(value.clone(), &Key::public()));
+        }
+        for (wire, label) in circuit.get_all_inputs().iter().zip(input_labels) {
+            labels[wire.0] = Some(label);
+        }
+
+        let gates = circuit.get_all_gates();
+        for id in order {
+            let gate = &gates[id];
+            let inputs: Option<Vec<Label<T>>> = gate
+                .get_inputs()
+                .iter()
+                .map(|wire| labels[wire.0].clone())
+                .collect();
+            let Some(inputs) = inputs else {
+                continue;
+            };
+            let label = Label(core::array::from_fn(|i| {
+                let components = inputs.iter().map(|label| label.0[i].clone()).collect();
+                gc.tables[id][i].evaluate(gate, components)
+            }));
+            labels[gate.get_output().0] = Some(label);
+        }
+
+        let outputs = circuit.get_all_outputs();
+        let mut empty: Vec<usize> = outputs
+            .iter()
+            .filter(|wire| labels[wire.0].is_none())
+            .map(|wire| wire.0)
+            .collect();
+        if !empty.is_empty() {
+            empty.sort_unstable();
+            empty.dedup();
+            return Err(EvalLocalError::EmptyWire { wire_ids: empty });
+        }
+        Ok(outputs
+            .iter()
+            .map(|wire| labels[wire.0].clone().unwrap())
+            .collect())
+    }
+}
+
+/// Pseudorandom generator expanding a seed with SHA-256 in counter mode.
+pub(crate) struct Prg {
+    seed: u64,
+    counter: u64,
+    block: [u8; 32],
+    // number of bytes of `block` already handed out
+    used: usize,
+}
+
+impl Prg {
+    pub(crate) fn new(seed: u64) -> Self {
+        Prg {
+            seed,
+            counter: 0,
+            block: [0; 32],
+            used: 32,
+        }
+    }
+}
+
+impl RngCore for Prg {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest.iter_mut() {
+            if self.used == self.block.len() {
+                let mut hasher = Sha256::new();
+                hasher.update(self.seed.to_le_bytes());
+                hasher.update(self.counter.to_le_bytes());
+                self.block = hasher.finalize().into();
+                self.counter += 1;
+                self.used = 0;
+            }
+            *byte = self.block[self.used];
+            self.used += 1;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        combine_input_labels, input_choices, Evaluator, GarbleOptions, GarbledCircuit,
+        GarbledTable, Garbler, Prg,
+    };
+    use crate::circuit::*;
+    use crate::eval_local::eval_local;
+    use crate::ot::{InsecureOt, ObliviousTransfer};
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn garble_add_gate() {
+        let mut circuit = Circuit::new();
+
+        // x + y = out
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, x, y);
+
+        let gc = Garbler::new(1).garble::<Fp>(&circuit);
+        let input_labels = gc.encode_inputs(vec![Fp::from(2), Fp::from(3)]).unwrap();
+        let output_labels = Evaluator::new(&circuit)
+            .evaluate(&gc, input_labels)
+            .unwrap();
+
+        assert_eq!(gc.decode_outputs(output_labels), Some(vec![Fp::from(5)]));
+    }
+
+    #[test]
+    fn garbled_evaluation_matches_eval_local() {
+        let mut circuit = Circuit::new();
+
+        // out1 = (x * y - z) * 7
+        // out2 = -(x + y + z + 1)
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let z = circuit.add_input();
+        let product = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, x, y, product);
+        let diff = circuit.create_new_wire();
+        circuit.add_gate(GateType::Sub, product, z, diff);
+        let scaled = circuit.add_scalar_mul(diff, Fp::from(7));
+        circuit.mark_output(scaled).unwrap();
+        let one = circuit.create_constant_wire(Fp::from(1));
+        let sum = circuit.add_many(&[x, y, z, one]);
+        let neg = circuit.add_neg(sum);
+        circuit.mark_output(neg).unwrap();
+
+        let gc = Garbler::new(42).garble::<Fp>(&circuit);
+        let evaluator = Evaluator::new(&circuit);
+        for inputs in [[1, 2, 3], [4, 5, 6], [0, 7, 9]] {
+            let inputs: Vec<Fp> = inputs.into_iter().map(Fp::from).collect();
+            let labels = gc.encode_inputs(inputs.clone()).unwrap();
+            let outputs = gc.decode_outputs(evaluator.evaluate(&gc, labels).unwrap());
+            assert_eq!(outputs, eval_local(&circuit, inputs).ok());
+        }
+    }
+
+    #[test]
+    fn half_gates_shrink_mul_tables() {
+        // out = x * y
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Mul, x, y);
+
+        let naive = Garbler::new(5).garble::<Fp>(&circuit);
+        let options = GarbleOptions {
+            half_gates: true,
+            ..GarbleOptions::default()
+        };
+        let optimized = Garbler::with_options(5, options).garble::<Fp>(&circuit);
+        let size = |gc: &GarbledCircuit<Fp>| -> usize {
+            gc.get_tables()
+                .iter()
+                .flatten()
+                .map(GarbledTable::size)
+                .sum()
+        };
+        assert_eq!(size(&naive), 6);
+        assert_eq!(size(&optimized), 4);
+
+        let evaluator = Evaluator::new(&circuit);
+        for gc in [naive, optimized] {
+            let labels = gc.encode_inputs(vec![Fp::from(6), Fp::from(7)]).unwrap();
+            let outputs = evaluator.evaluate(&gc, labels).unwrap();
+            assert_eq!(gc.decode_outputs(outputs), Some(vec![Fp::from(42)]));
+        }
+    }
+
+    #[test]
+    fn half_gates_match_eval_local() {
+        // out = (x * y) * (y * 3) + x * x
+        // the Mul by the constant wire 3 keeps its full table
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let three = circuit.create_constant_wire(Fp::from(3));
+        let product = circuit.add_output_gate(GateType::Mul, x, y);
+        let scaled = circuit.add_output_gate(GateType::Mul, y, three);
+        let chained = circuit.add_output_gate(GateType::Mul, product, scaled);
+        let square = circuit.add_output_gate(GateType::Mul, x, x);
+        circuit.add_output_gate(GateType::Add, chained, square);
+
+        let options = GarbleOptions {
+            half_gates: true,
+            ..GarbleOptions::default()
+        };
+        let gc = Garbler::with_options(9, options).garble::<Fp>(&circuit);
+        assert!(matches!(gc.get_tables()[1][0], GarbledTable::Mul { .. }));
+        assert!(matches!(
+            gc.get_tables()[2][0],
+            GarbledTable::HalfMul { .. }
+        ));
+
+        let evaluator = Evaluator::new(&circuit);
+        for inputs in [[2, 5], [0, 1], [7, 0]] {
+            let inputs: Vec<Fp> = inputs.into_iter().map(Fp::from).collect();
+            let labels = gc.encode_inputs(inputs.clone()).unwrap();
+            let outputs = gc.decode_outputs(evaluator.evaluate(&gc, labels).unwrap());
+            assert_eq!(outputs, eval_local(&circuit, inputs).ok());
+        }
+    }
+
+    #[test]
+    fn free_addition_needs_no_tables() {
+        // out = -(x + y - z) + (x + y + z)
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let z = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        let diff = circuit.create_new_wire();
+        circuit.add_gate(GateType::Sub, sum, z, diff);
+        let neg = circuit.add_neg(diff);
+        let total = circuit.add_many(&[x, y, z]);
+        circuit.add_output_gate(GateType::Add, neg, total);
+
+        let options = GarbleOptions {
+            free_addition: true,
+            ..GarbleOptions::default()
+        };
+        let gc = Garbler::with_options(11, options).garble::<Fp>(&circuit);
+        assert!(gc
+            .get_tables()
+            .iter()
+            .flatten()
+            .all(|table| *table == GarbledTable::Free));
+
+        let evaluator = Evaluator::new(&circuit);
+        for inputs in [[1, 2, 3], [4, 0, 9]] {
+            let inputs: Vec<Fp> = inputs.into_iter().map(Fp::from).collect();
+            let labels = gc.encode_inputs(inputs.clone()).unwrap();
+            let outputs = gc.decode_outputs(evaluator.evaluate(&gc, labels).unwrap());
+            assert_eq!(outputs, eval_local(&circuit, inputs).ok());
+        }
+    }
+
+    #[test]
+    fn all_options_match_eval_local() {
+        // out = (x * y + 2) * (x - y)
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let two = circuit.create_constant_wire(Fp::from(2));
+        let product = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, x, y, product);
+        let shifted = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, product, two, shifted);
+        let diff = circuit.create_new_wire();
+        circuit.add_gate(GateType::Sub, x, y, diff);
+        circuit.add_output_gate(GateType::Mul, shifted, diff);
+
+        let options = GarbleOptions {
+            half_gates: true,
+            free_addition: true,
+        };
+        let gc = Garbler::with_options(12, options).garble::<Fp>(&circuit);
+        let size: usize = gc
+            .get_tables()
+            .iter()
+            .flatten()
+            .map(GarbledTable::size)
+            .sum();
+        assert_eq!(size, 8);
+
+        let evaluator = Evaluator::new(&circuit);
+        for inputs in [[3, 5], [8, 1]] {
+            let inputs: Vec<Fp> = inputs.into_iter().map(Fp::from).collect();
+            let labels = gc.encode_inputs(inputs.clone()).unwrap();
+            let outputs = gc.decode_outputs(evaluator.evaluate(&gc, labels).unwrap());
+            assert_eq!(outputs, eval_local(&circuit, inputs).ok());
+        }
+    }
+
+    #[test]
+    fn evaluator_inputs_by_oblivious_transfer() {
+        let mut circuit = Circuit::new();
+
+        // out = x * y + y
+        // x belongs to the garbler and y to the evaluator
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let product = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, x, y, product);
+        circuit.add_output_gate(GateType::Add, product, y);
+
+        let gc = Garbler::new(3).garble::<Fp>(&circuit);
+        let garbler_label = gc.encode_input(0, Fp::from(6));
+
+        let (mut sender, mut receiver) = InsecureOt::pair();
+        sender
+            .send(&gc.input_label_pairs(1..2, &mut Prg::new(4)))
+            .unwrap();
+        let choices = input_choices(&[Fp::from(9)]);
+        let evaluator_labels = combine_input_labels(receiver.receive(&choices).unwrap());
+        assert_eq!(evaluator_labels, vec![gc.encode_input(1, Fp::from(9))]);
+
+        let mut labels = vec![garbler_label];
+        labels.extend(evaluator_labels);
+        let outputs = Evaluator::new(&circuit).evaluate(&gc, labels).unwrap();
+        assert_eq!(gc.decode_outputs(outputs), Some(vec![Fp::from(63)]));
+    }
+
+    #[test]
+    fn tampered_tables_dont_fit() {
+        let mut circuit = Circuit::new();
+
+        // out = -x
+        let x = circuit.add_input();
+        let out = circuit.add_neg(x);
+        circuit.mark_output(out).unwrap();
+
+        let mut gc = Garbler::new(1).garble::<Fp>(&circuit);
+        assert!(gc.fits(&circuit));
+
+        let mul = GarbledTable::Mul {
+            x_mask: Fp::from(1),
+            y_mask: Fp::from(2),
+            offset: Fp::from(3),
+        };
+        gc.tables[0] = [mul.clone(), mul];
+        assert!(!gc.fits(&circuit), "Neg gate can't have a Mul table");
+
+        gc.tables.clear();
+        assert!(!gc.fits(&circuit));
+    }
+}
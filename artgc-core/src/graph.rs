@@ -0,0 +1,212 @@
+//! Conversion between `Circuit` and `petgraph` graphs,
+//! so that graph algorithms from the petgraph ecosystem can be run on circuits.
+//!
+//! Input wires, gates and output wires become nodes.
+//! Every edge is one read of a wire: it goes from the node producing the wire to the node reading it.
+//! A gate reading the same wire twice (x * x) has two edges from the same node.
+//! Incoming edges of a gate node are ordered by insertion, so the first one is x and the second one is y.
+//! Constant wires are not represented, since their values can't be stored in a `NodeKind`.
+
+use crate::circuit::{Circuit, Gate, GateId, GateType, WireId};
+use crate::error::{CircuitError, CircuitResult};
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Input { wire_id: usize },
+    Gate { id: GateId, gate_type: GateType },
+    Output { wire_id: usize },
+}
+
+impl Circuit {
+    /// Convert the circuit into a directed graph.
+    /// Nodes are added in the order: inputs, gates, outputs.
+    pub fn to_petgraph(&self) -> DiGraph<NodeKind, ()> {
+        let mut graph = DiGraph::new();
+        let mut producer_nodes: Vec<Option<NodeIndex>> = vec![None; self.get_wire_count()];
+
+        for input in self.get_all_inputs() {
+            let node = graph.add_node(NodeKind::Input { wire_id: input.0 });
+            producer_nodes[input.0] = Some(node);
+        }
+
+        let gates = self.get_all_gates();
+        let mut gate_nodes = vec![];
+        for (id, gate) in gates.iter().enumerate() {
+            let node = graph.add_node(NodeKind::Gate {
+                id,
+                gate_type: gate.gate_type(),
+            });
+            producer_nodes[gate.get_output().0] = Some(node);
+            gate_nodes.push(node);
+        }
+
+        for (id, gate) in gates.iter().enumerate() {
+            for input in gate.get_inputs() {
+                if let Some(from) = producer_nodes[input.0] {
+                    graph.add_edge(from, gate_nodes[id], ());
+                }
+            }
+        }
+
+        for out in self.get_all_outputs() {
+            let node = graph.add_node(NodeKind::Output { wire_id: out.0 });
+            if let Some(from) = producer_nodes[out.0] {
+                graph.add_edge(from, node, ());
+            }
+        }
+
+        graph
+    }
+
+    /// Best-effort conversion of a graph shaped like `to_petgraph` output back into a circuit.
+    /// Wire and gate ids are reassigned: ids stored in the node weights are ignored.
+    /// `Neg` gate nodes must have exactly one incoming edge, `Add` gate nodes at least one
+    /// (other than two, they become `AddMany` gates), other gate nodes exactly two, and output nodes exactly one.
+    pub fn from_petgraph(graph: &DiGraph<NodeKind, ()>) -> CircuitResult<Circuit> {
+        let mut circuit = Circuit::new();
+        let mut node_wires: Vec<Option<WireId>> = vec![None; graph.node_count()];
+
+        // every input and every gate produces a wire
+        for node in graph.node_indices() {
+            match graph[node] {
+                NodeKind::Input { .. } | NodeKind::Gate { .. } => {
+                    node_wires[node.index()] = Some(circuit.create_new_wire());
+                }
+                NodeKind::Output { .. } => {}
+            }
+        }
+
+        let incoming_wires = |node: NodeIndex| -> Vec<Option<WireId>> {
+            let mut edges: Vec<_> = graph.edges_directed(node, Direction::Incoming).collect();
+            edges.sort_by_key(|edge| edge.id());
+            edges
+                .into_iter()
+                .map(|edge| node_wires[edge.source().index()])
+                .collect()
+        };
+
+        for node in graph.node_indices() {
+            let invalid = CircuitError::InvalidGraphNode {
+                node_index: node.index(),
+            };
+            match graph[node] {
+                NodeKind::Input { .. } => {
+                    circuit.mark_input(node_wires[node.index()].unwrap())?;
+                }
+                NodeKind::Gate { gate_type, .. } => {
+                    let Some(inputs) = incoming_wires(node).into_iter().collect::<Option<Vec<_>>>()
+                    else {
+                        return Err(invalid);
+                    };
+                    let id = circuit.get_gate_count();
+                    let out = node_wires[node.index()].unwrap();
+                    match (gate_type, &inputs[..]) {
+                        (GateType::Neg, [x]) => circuit.push_gate(Gate::Neg { id, x: *x, out }),
+                        (GateType::Neg, _) => return Err(invalid),
+                        (_, [x, y]) => {
+                            circuit.add_gate(gate_type, *x, *y, out);
+                        }
+                        (GateType::Add, [_, ..]) => {
+                            circuit.push_gate(Gate::AddMany { id, inputs, out })
+                        }
+                        _ => return Err(invalid),
+                    }
+                }
+                NodeKind::Output { .. } => match incoming_wires(node)[..] {
+                    [Some(wire)] => circuit.mark_output(wire)?,
+                    _ => return Err(invalid),
+                },
+            }
+        }
+
+        Ok(circuit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NodeKind;
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+    use petgraph::graph::DiGraph;
+
+    #[test]
+    fn petgraph_round_trip() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        let graph = circuit.to_petgraph();
+        // 3 inputs, 2 gates and 2 outputs
+        assert_eq!(graph.node_count(), 7);
+        // 2 reads per gate and 1 per output
+        assert_eq!(graph.edge_count(), 6);
+
+        let restored = Circuit::from_petgraph(&graph).unwrap();
+        assert_eq!(restored.get_gate_count(), 2);
+        assert_eq!(restored.get_all_inputs().len(), 3);
+        assert_eq!(restored.get_all_outputs().len(), 2);
+        assert_eq!(restored.to_petgraph().edge_count(), 6);
+    }
+
+    #[test]
+    fn petgraph_round_trip_with_neg_and_add_many() {
+        // out = -(in1 + in2 + in3)
+        let mut circuit = Circuit::new();
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let in3 = circuit.add_input();
+        let sum = circuit.add_many(&[in1, in2, in3]);
+        let out = circuit.add_neg(sum);
+        circuit.mark_output(out).unwrap();
+
+        let graph = circuit.to_petgraph();
+        let restored = Circuit::from_petgraph(&graph).unwrap();
+        assert_eq!(restored.get_gate_count(), 2);
+        for inputs in [[1, 2, 3], [4, 0, 9]] {
+            let inputs: Vec<Fp> = inputs.into_iter().map(Fp::from).collect();
+            assert_eq!(
+                eval_local(&restored, inputs.clone()),
+                eval_local(&circuit, inputs)
+            );
+        }
+    }
+
+    #[test]
+    fn neg_node_with_two_edges_is_invalid() {
+        let mut graph = DiGraph::new();
+        let x = graph.add_node(NodeKind::Input { wire_id: 0 });
+        let neg = graph.add_node(NodeKind::Gate {
+            id: 0,
+            gate_type: GateType::Neg,
+        });
+        graph.add_edge(x, neg, ());
+        graph.add_edge(x, neg, ());
+
+        assert_eq!(
+            Circuit::from_petgraph(&graph).map(|_| ()),
+            Err(CircuitError::InvalidGraphNode { node_index: 1 })
+        );
+    }
+}
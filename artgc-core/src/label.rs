@@ -0,0 +1,153 @@
+//! Wire labels: encoded forms of wire values, which can only be decoded with the key of their wire.
+//!
+//! A label has two components, each masked additively with its own mask of the key.
+//! Both components decode to the same value with the right key, so a label decoded with another key
+//! gives two different values and is rejected, unless the masks of both keys differ by the same amount.
+//! Masking is linear, so labels can be computed on by the evaluator, see `garble`.
+//!
+//! The labels of `v + 1` and `v` differ by `delta` under every key, so all wires share one offset,
+//! like the global offset of Free-XOR. Unlike in Free-XOR, the offset is public: it is 1 in each component,
+//! and the keys alone hide the values.
+//! A linear gate whose output key is `linear_key` of its input keys maps input labels to its output label,
+//! so it can be evaluated without any garbled table.
+
+use crate::circuit::Gate;
+use crate::ring::Ring;
+
+/// Encoded form of a wire value, see `encode`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label<T>(pub [T; 2]);
+
+/// Masks of a wire, one for each component of its labels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Key<T>(pub [T; 2]);
+
+impl<T: Ring> Key<T> {
+    /// Key of a public wire, whose labels are its values.
+    pub fn public() -> Self {
+        Key([T::zero(), T::zero()])
+    }
+}
+
+/// Labels of the values 0 and 1 on a wire, like the two labels of a wire of a boolean garbled circuit.
+/// Masking is additive, so they determine the label of every other value too, see `select`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabelPair<T> {
+    pub zero: Label<T>,
+    pub one: Label<T>,
+}
+
+impl<T: Ring> LabelPair<T> {
+    pub fn new(key: &Key<T>) -> Self {
+        LabelPair {
+            zero: encode(T::zero(), key),
+            one: encode(T::one(), key),
+        }
+    }
+
+    /// Returns the label of `value`, which is `zero + value * (one - zero)`.
+    pub fn select(&self, value: T) -> Label<T> {
+        Label(core::array::from_fn(|i| {
+            let step = self.one.0[i].clone() - &self.zero.0[i];
+            self.zero.0[i].clone() + &(value.clone() * &step)
+        }))
+    }
+}
+
+pub fn encode<T: Ring>(value: T, key: &Key<T>) -> Label<T> {
+    Label(core::array::from_fn(|i| value.clone() + &key.0[i]))
+}
+
+/// Difference between the labels of `value + 1` and `value` on any wire.
+pub fn delta<T: Ring>() -> Label<T> {
+    Label([T::one(), T::one()])
+}
+
+/// Returns the key of the output wire of the linear gate `gate` whose input wires have `input_keys`, in order,
+/// for which applying the gate to the input labels gives the output label.
+/// It is `gate` applied to the input keys, since the values and `delta` are shared by all the inputs.
+///
+/// The output key is determined by the input keys, so it must not be used on wires whose keys have to be
+/// independent. See `garble` for the security trade-off.
+pub fn linear_key<T: Ring>(gate: &Gate, input_keys: &[Key<T>]) -> Key<T> {
+    Key(core::array::from_fn(|i| {
+        gate.evaluate(input_keys.iter().map(|key| key.0[i].clone()))
+    }))
+}
+
+/// Returns the value of `label`, or None if `label` was not encoded with `key`.
+pub fn decode<T: Ring>(label: Label<T>, key: &Key<T>) -> Option<T> {
+    let [first, second] = label.0;
+    let [first_mask, second_mask] = &key.0;
+    let value = first - first_mask;
+    (value == second - second_mask).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, delta, encode, linear_key, Key, Label, LabelPair};
+    use crate::circuit::{Gate, WireId};
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn decode_encoded_value() {
+        let key = Key([Fp::from(11), Fp::from(29)]);
+        for value in [0, 1, 42] {
+            let value = Fp::from(value);
+            assert_eq!(decode(encode(value, &key), &key), Some(value));
+        }
+    }
+
+    #[test]
+    fn wrong_key_is_rejected() {
+        let key = Key([Fp::from(11), Fp::from(29)]);
+        let other = Key([Fp::from(11), Fp::from(30)]);
+        assert_eq!(decode(encode(Fp::from(42), &key), &other), None);
+    }
+
+    #[test]
+    fn labels_share_delta() {
+        for key in [
+            Key([Fp::from(5), Fp::from(8)]),
+            Key([Fp::from(1), Fp::from(0)]),
+        ] {
+            let step = Label(core::array::from_fn(|i| {
+                encode(Fp::from(43), &key).0[i] - encode(Fp::from(42), &key).0[i]
+            }));
+            assert_eq!(step, delta());
+        }
+    }
+
+    #[test]
+    fn linear_key_of_sub_gate() {
+        let gate = Gate::Sub {
+            id: 0,
+            x: WireId(0),
+            y: WireId(1),
+            out: WireId(2),
+        };
+        let keys = [
+            Key([Fp::from(5), Fp::from(8)]),
+            Key([Fp::from(2), Fp::from(9)]),
+        ];
+        let out_key = linear_key(&gate, &keys);
+
+        let labels = [
+            encode(Fp::from(10), &keys[0]),
+            encode(Fp::from(4), &keys[1]),
+        ];
+        let out_label = Label(core::array::from_fn(|i| labels[0].0[i] - labels[1].0[i]));
+        assert_eq!(decode(out_label, &out_key), Some(Fp::from(6)));
+    }
+
+    #[test]
+    fn label_pair_selects_any_value() {
+        let key = Key([Fp::from(5), Fp::from(8)]);
+        let pair = LabelPair::new(&key);
+        assert_eq!(pair.select(Fp::from(0)), pair.zero);
+        assert_eq!(pair.select(Fp::from(1)), pair.one);
+        assert_eq!(pair.select(Fp::from(42)), encode(Fp::from(42), &key));
+    }
+}
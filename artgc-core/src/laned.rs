@@ -0,0 +1,158 @@
+//! Evaluation of circuits whose wires carry a fixed-width vector of ring elements.
+//! Every gate is applied lane-wise, so one evaluation computes `LANES` independent instances of the circuit.
+//! This is the same idea as SIMD packing in FHE and MPC.
+
+use crate::circuit::Circuit;
+use crate::eval_local::{constant_values, eval_local_with_constants, EvalLocalError};
+use crate::ring::Ring;
+use std::ops::{Add, Mul, Sub};
+
+/// `LANES` ring elements carried by a single wire.
+/// It is a ring itself with lane-wise operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lanes<T: Ring, const LANES: usize>(pub [T; LANES]);
+
+impl<T: Ring, const LANES: usize> Add for Lanes<T, LANES> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let mut rhs = rhs.0.into_iter();
+        Lanes(self.0.map(|lhs| lhs + rhs.next().unwrap()))
+    }
+}
+
+impl<'a, T: Ring, const LANES: usize> Add<&'a Lanes<T, LANES>> for Lanes<T, LANES> {
+    type Output = Self;
+
+    fn add(self, rhs: &'a Self) -> Self {
+        let mut rhs = rhs.0.iter();
+        Lanes(self.0.map(|lhs| lhs + rhs.next().unwrap()))
+    }
+}
+
+impl<T: Ring, const LANES: usize> Sub for Lanes<T, LANES> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let mut rhs = rhs.0.into_iter();
+        Lanes(self.0.map(|lhs| lhs - rhs.next().unwrap()))
+    }
+}
+
+impl<'a, T: Ring, const LANES: usize> Sub<&'a Lanes<T, LANES>> for Lanes<T, LANES> {
+    type Output = Self;
+
+    fn sub(self, rhs: &'a Self) -> Self {
+        let mut rhs = rhs.0.iter();
+        Lanes(self.0.map(|lhs| lhs - rhs.next().unwrap()))
+    }
+}
+
+impl<T: Ring, const LANES: usize> Mul for Lanes<T, LANES> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut rhs = rhs.0.into_iter();
+        Lanes(self.0.map(|lhs| lhs * rhs.next().unwrap()))
+    }
+}
+
+impl<'a, T: Ring, const LANES: usize> Mul<&'a Lanes<T, LANES>> for Lanes<T, LANES> {
+    type Output = Self;
+
+    fn mul(self, rhs: &'a Self) -> Self {
+        let mut rhs = rhs.0.iter();
+        Lanes(self.0.map(|lhs| lhs * rhs.next().unwrap()))
+    }
+}
+
+impl<T: Ring, const LANES: usize> Ring for Lanes<T, LANES> {
+    fn zero() -> Self {
+        Lanes(std::array::from_fn(|_| T::zero()))
+    }
+
+    fn one() -> Self {
+        Lanes(std::array::from_fn(|_| T::one()))
+    }
+}
+
+/// Evaluate a circuit on `LANES` input assignments at once.
+/// `input_values[i][lane]` is the value of i-th input wire in the given lane,
+/// and the result is laid out the same way for the output wires.
+/// Constant wires of ring `T` carry their value in every lane.
+pub fn eval_local_laned<T: Ring, const LANES: usize>(
+    circuit: &Circuit,
+    input_values: Vec<[T; LANES]>,
+) -> Result<Vec<[T; LANES]>, EvalLocalError> {
+    let inputs = input_values.into_iter().map(Lanes).collect();
+    let constants = constant_values::<T>(circuit)
+        .into_iter()
+        .map(|value| value.map(|value| Lanes(std::array::from_fn(|_| value.clone()))))
+        .collect();
+    let outputs = eval_local_with_constants::<Lanes<T, LANES>>(circuit, inputs, constants)?;
+
+    Ok(outputs.into_iter().map(|lanes| lanes.0).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::eval_local_laned;
+    use crate::circuit::*;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn laned_matches_independent_evaluations() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        let lanes: [[u64; 3]; 4] = [[1, 2, 3], [4, 5, 6], [7, 8, 9], [0, 10, 100]];
+
+        let inputs: Vec<[Fp; 4]> = (0..3)
+            .map(|i| std::array::from_fn(|lane| Fp::from(lanes[lane][i])))
+            .collect();
+        let result = eval_local_laned(&circuit, inputs).unwrap();
+
+        for (lane, values) in lanes.iter().enumerate() {
+            let expected = eval_local(&circuit, values.map(Fp::from).to_vec()).unwrap();
+            let actual: Vec<Fp> = result.iter().map(|out| out[lane]).collect();
+            assert_eq!(actual, expected, "Lane {} should match eval_local", lane);
+        }
+    }
+
+    #[test]
+    fn constants_are_broadcast_to_every_lane() {
+        let mut circuit = Circuit::new();
+
+        // out = in * 3 + 2
+        let input = circuit.add_input();
+        let three = circuit.create_constant_wire(Fp::from(3));
+        let two = circuit.create_constant_wire(Fp::from(2));
+        let scaled = circuit.create_new_wire();
+        circuit.add_gate(GateType::ScalarMul, input, three, scaled);
+        circuit.add_output_gate(GateType::Add, scaled, two);
+
+        let inputs = vec![[Fp::from(1), Fp::from(5)]];
+        assert_eq!(
+            eval_local_laned(&circuit, inputs),
+            Ok(vec![[Fp::from(5), Fp::from(17)]])
+        );
+    }
+}
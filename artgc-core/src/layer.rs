@@ -0,0 +1,341 @@
+//! Group gates into layers.
+//! Wires marked as inputs of the circuit and constant wires have layer 0.
+//! A gate belongs to the highest layer of its input wires, and its output wire has the layer after it.
+//! All the gates in the same layer only depend on gates of lower layers,
+//! so they can be evaluated independently of each other.
+
+use crate::circuit::{Circuit, GateId, GateType};
+use crate::error::{CircuitError, CircuitResult};
+
+/// Plain data description of a gate.
+/// It doesn't borrow the circuit, so it can be handed to an external executor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GateDescriptor {
+    pub id: GateId,
+    pub gate_type: GateType,
+    pub inputs: Vec<usize>,
+    pub output: usize,
+}
+
+impl Circuit {
+    /// Returns gate ids grouped by layer. Index of the outer vector is the layer number.
+    /// Gates in a layer are sorted by id.
+    ///
+    /// The layers are the ones evaluation uses, see `Circuit::layers`, so they are computed once until the circuit changes.
+    /// Returns an error if some gates can never be scheduled,
+    /// either because one of their input wires is not driven by anything or because they are on a cycle.
+    pub fn gate_layers(&self) -> CircuitResult<Vec<Vec<GateId>>> {
+        let gates = self.get_all_gates();
+        let layers = self.layers();
+
+        let mut is_scheduled = vec![false; gates.len()];
+        for id in layers.gate_layers.iter().flatten() {
+            is_scheduled[*id] = true;
+        }
+        let stuck: Vec<GateId> = (0..gates.len()).filter(|id| !is_scheduled[*id]).collect();
+        if !stuck.is_empty() {
+            let mut is_driven = vec![false; self.get_wire_count()];
+            let constants = self.get_constants().iter().map(|c| c.get_wire_id());
+            let outputs = gates.iter().map(|gate| gate.get_output());
+            for wire in self
+                .get_all_inputs()
+                .iter()
+                .copied()
+                .chain(constants)
+                .chain(outputs)
+            {
+                is_driven[wire.0] = true;
+            }
+
+            for id in stuck.iter() {
+                let inputs = gates[*id].get_inputs();
+                if let Some(w) = inputs.into_iter().find(|w| !is_driven[w.0]) {
+                    return Err(CircuitError::UndrivenWire { wire_id: w.0 });
+                }
+            }
+            // every input of the stuck gates is driven, so they wait on each other.
+            for id in stuck {
+                let inputs = gates[id].get_inputs();
+                if let Some(w) = inputs
+                    .into_iter()
+                    .find(|w| layers.wire_layers[w.0].is_none())
+                {
+                    return Err(CircuitError::CyclicPath {
+                        gate_id: id,
+                        wire_id: w.0,
+                    });
+                }
+            }
+        }
+
+        let mut gate_layers = layers.gate_layers.clone();
+        for layer in gate_layers.iter_mut() {
+            layer.sort_unstable();
+        }
+
+        Ok(gate_layers)
+    }
+
+    /// Returns gate ids grouped by the round of an MPC protocol in which additions are local.
+    /// Index of the outer vector is the round number. Gates in a round are sorted by id.
+    ///
+    /// Input wires are available in round 0. A gate runs in the round all of its input wires are available,
+    /// and its output wire is available in the same round for Add, Sub, Neg and ScalarMul gates
+    /// and in the next round for Mul gates.
+    /// Returns the same errors as `gate_layers`.
+    pub fn mpc_rounds(&self) -> CircuitResult<Vec<Vec<GateId>>> {
+        let gates = self.get_all_gates();
+        let mut wire_rounds = vec![0; self.get_wire_count()];
+        let mut rounds: Vec<Vec<GateId>> = vec![];
+
+        for id in self.gate_layers()?.into_iter().flatten() {
+            let gate = &gates[id];
+            let round = gate
+                .get_inputs()
+                .iter()
+                .map(|w| wire_rounds[w.0])
+                .max()
+                .unwrap();
+
+            if rounds.len() <= round {
+                rounds.resize(round + 1, vec![]);
+            }
+            rounds[round].push(id);
+
+            wire_rounds[gate.get_output().0] = match gate.gate_type() {
+                GateType::Add | GateType::Sub | GateType::Neg | GateType::ScalarMul => round,
+                GateType::Mul => round + 1,
+            };
+        }
+
+        for round in rounds.iter_mut() {
+            round.sort_unstable();
+        }
+
+        Ok(rounds)
+    }
+
+    /// Returns the number of layers of `gate_layers`, which is the number of gates on the longest path.
+    /// A circuit without gates has depth 0.
+    /// Returns the same errors as `gate_layers`.
+    pub fn depth(&self) -> CircuitResult<usize> {
+        Ok(self.gate_layers()?.len())
+    }
+
+    /// Returns the largest number of Mul gates on any path, which bounds the rounds of protocols
+    /// where additions are local, see `mpc_rounds`. ScalarMul gates multiply by a public value, so they don't count.
+    /// A circuit without Mul gates has multiplicative depth 0.
+    /// Returns the same errors as `gate_layers`.
+    pub fn mul_depth(&self) -> CircuitResult<usize> {
+        let gates = self.get_all_gates();
+        let mut wire_depths = vec![0; self.get_wire_count()];
+        let mut max_depth = 0;
+
+        for id in self.gate_layers()?.into_iter().flatten() {
+            let gate = &gates[id];
+            let input_depth = gate
+                .get_inputs()
+                .iter()
+                .map(|w| wire_depths[w.0])
+                .max()
+                .unwrap();
+            let depth = match gate.gate_type() {
+                GateType::Mul => input_depth + 1,
+                GateType::Add | GateType::Sub | GateType::Neg | GateType::ScalarMul => input_depth,
+            };
+            wire_depths[gate.get_output().0] = depth;
+            max_depth = max_depth.max(depth);
+        }
+
+        Ok(max_depth)
+    }
+
+    /// Returns the layered gate grouping as ready-to-dispatch batches.
+    /// Gates in a batch are independent of each other,
+    /// and a batch only depends on the outputs of the batches before it.
+    pub fn parallel_batches(&self) -> CircuitResult<Vec<Vec<GateDescriptor>>> {
+        let gates = self.get_all_gates();
+        Ok(self
+            .gate_layers()?
+            .into_iter()
+            .map(|layer| {
+                layer
+                    .into_iter()
+                    .map(|id| {
+                        let gate = &gates[id];
+                        GateDescriptor {
+                            id,
+                            gate_type: gate.gate_type(),
+                            inputs: gate.get_inputs().iter().map(|w| w.0).collect(),
+                            output: gate.get_output().0,
+                        }
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GateDescriptor;
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::eval_local::{eval_local, eval_local_best_effort};
+    use crate::test_utils::Fp;
+
+    // Circuit
+    // out1 = in1 + in2
+    // out2 = (in1 + in2) * in3
+    // out3 = in2 * in3
+    fn three_outputs() -> Circuit {
+        let mut circuit = Circuit::new();
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let in3 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        let out3 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+        circuit.add_gate(GateType::Mul, out1, in3, out2);
+        circuit.add_gate(GateType::Mul, in2, in3, out3);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+        circuit.mark_output(out3).unwrap();
+        circuit
+    }
+
+    #[test]
+    fn layers_of_circuit() {
+        let circuit = three_outputs();
+        assert_eq!(circuit.gate_layers(), Ok(vec![vec![0, 2], vec![1]]));
+    }
+
+    #[test]
+    fn batches_match_layers() {
+        let circuit = three_outputs();
+        let batches = circuit.parallel_batches().unwrap();
+        assert_eq!(
+            batches,
+            vec![
+                vec![
+                    GateDescriptor {
+                        id: 0,
+                        gate_type: GateType::Add,
+                        inputs: vec![0, 1],
+                        output: 3,
+                    },
+                    GateDescriptor {
+                        id: 2,
+                        gate_type: GateType::Mul,
+                        inputs: vec![1, 2],
+                        output: 5,
+                    },
+                ],
+                vec![GateDescriptor {
+                    id: 1,
+                    gate_type: GateType::Mul,
+                    inputs: vec![3, 2],
+                    output: 4,
+                }],
+            ]
+        );
+    }
+
+    #[test]
+    fn additions_do_not_take_rounds() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // a = x + y (gate0)
+        // b = a + x (gate1)
+        // c = b * y (gate2)
+        // d = c + c (gate3)
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        let c = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, a);
+        circuit.add_gate(GateType::Add, a, x, b);
+        circuit.add_gate(GateType::Mul, b, y, c);
+        circuit.add_output_gate(GateType::Add, c, c);
+
+        assert_eq!(
+            circuit.gate_layers(),
+            Ok(vec![vec![0], vec![1], vec![2], vec![3]])
+        );
+        assert_eq!(circuit.mpc_rounds(), Ok(vec![vec![0, 1, 2], vec![3]]));
+    }
+
+    #[test]
+    fn layers_are_shared_with_evaluation() {
+        let mut circuit = Circuit::new();
+
+        // out is driven twice: out = in1 + in2 (gate0) and out = in1 * in2 (gate1)
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let out = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out);
+        circuit.add_gate(GateType::Mul, in1, in2, out);
+        circuit.mark_output(out).unwrap();
+
+        assert_eq!(circuit.gate_layers(), Ok(vec![vec![0, 1]]));
+        let inputs = vec![Fp::from(2), Fp::from(3)];
+        assert_eq!(
+            eval_local(&circuit, inputs.clone()),
+            Ok(vec![Fp::from(5)]),
+            "The first driver should give the value"
+        );
+        let (values, errors) = eval_local_best_effort(&circuit, inputs);
+        assert_eq!(values.get(&out), Some(&Fp::from(5)));
+        assert!(errors.is_empty());
+        assert_eq!(circuit.layer_computations.get(), 1);
+    }
+
+    #[test]
+    fn layers_of_undriven_gate() {
+        let mut circuit = Circuit::new();
+        let in1 = circuit.create_new_wire();
+        let floating = circuit.create_new_wire();
+        let out = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, floating, out);
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_output(out).unwrap();
+
+        assert_eq!(
+            circuit.gate_layers(),
+            Err(CircuitError::UndrivenWire { wire_id: 1 })
+        );
+    }
+
+    #[test]
+    fn depth_of_add_then_mul() {
+        let mut circuit = Circuit::new();
+
+        // out = (x + y) * z
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let z = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        circuit.add_output_gate(GateType::Mul, sum, z);
+
+        assert_eq!(circuit.depth(), Ok(2));
+        assert_eq!(circuit.mul_depth(), Ok(1));
+    }
+
+    #[test]
+    fn depth_of_empty_circuit() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        circuit.mark_output(x).unwrap();
+
+        assert_eq!(circuit.depth(), Ok(0));
+        assert_eq!(circuit.mul_depth(), Ok(0));
+    }
+}
@@ -1,5 +1,91 @@
+//! Without the default `std` feature, only the core circuit representation, `CircuitBuilder`,
+//! `eval_local`, `detect_cycle`, `compose`, `dyn_ring`, `ring`, `rings` and `topo` are built, on top of `alloc`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod ascii;
+#[cfg(feature = "std")]
+pub mod assignment;
+#[cfg(feature = "std")]
+pub mod bristol;
+pub mod builder;
 pub mod circuit;
+pub mod compose;
+#[cfg(feature = "std")]
+pub mod content_hash;
+#[cfg(feature = "std")]
+pub mod dead_gates;
 pub mod detect_cycle;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod dot;
+pub mod dyn_ring;
+#[cfg(feature = "std")]
+pub mod equivalence;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod eval_lazy;
 pub mod eval_local;
-mod ring;
+#[cfg(feature = "std")]
+pub mod fold;
+#[cfg(feature = "std")]
+pub mod garble;
+#[cfg(feature = "petgraph")]
+pub mod graph;
+#[cfg(feature = "std")]
+pub mod label;
+#[cfg(feature = "std")]
+pub mod laned;
+#[cfg(feature = "std")]
+pub mod layer;
+#[cfg(feature = "std")]
+pub mod lint;
+#[cfg(feature = "net")]
+pub mod net;
+#[cfg(feature = "std")]
+pub mod ot;
+#[cfg(feature = "std")]
+pub mod polynomial;
+pub mod ring;
+pub mod rings;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(test)]
+mod test_utils;
+#[cfg(any(feature = "testing", test))]
+pub mod testing;
+pub mod topo;
+#[cfg(feature = "std")]
+pub mod trace;
+
+/// Runs with and without the `std` feature, so `cargo test --no-default-features`
+/// exercises the items of the `alloc`-only build. The test harness itself links std,
+/// so whether they compile without it is checked by `cargo build --no-default-features`.
+#[cfg(test)]
+mod tests {
+    use crate::circuit::{Circuit, GateType};
+    use crate::detect_cycle::detect_cycle;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn core_builds_and_evaluates() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        circuit.add_output_gate(GateType::Mul, sum, y);
+
+        assert_eq!(detect_cycle(&circuit), None);
+        assert_eq!(
+            eval_local(&circuit, alloc::vec![Fp::from(2), Fp::from(3)]),
+            Ok(alloc::vec![Fp::from(15)])
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Soft diagnostics of a circuit.
+//! Unlike `CircuitError`, a warning doesn't make the circuit unusable,
+//! but it usually points at a mistake or at wasted gates.
+
+use crate::analysis::backward_reachable;
+use crate::circuit::{Circuit, GateId, GateType, WireConnection, WireId};
+use std::fmt::Display;
+
+/// Wires read by more gates than this are reported as `CircuitWarning::HighFanOut`.
+pub const HIGH_FAN_OUT_THRESHOLD: usize = 16;
+
+/// Chains of this many gates or more are reported as `CircuitWarning::UnbalancedChain`.
+pub const UNBALANCED_CHAIN_THRESHOLD: usize = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CircuitWarning {
+    /// Wire is created but neither marked as input/output nor connected to any gate.
+    UnusedWire { wire_id: usize },
+    /// Wire is connected, but there is no path from it to any output wire.
+    DeadWire { wire_id: usize },
+    /// Wire is read by more than `HIGH_FAN_OUT_THRESHOLD` gates.
+    HighFanOut { wire_id: usize, fan_out: usize },
+    /// Wire is marked as output more than once.
+    RedundantOutput { wire_id: usize },
+    /// Gates of the same type feed each other in a line of `length` gates ending at `gate_id`.
+    /// Rebalancing the chain into a tree reduces the depth of the circuit.
+    /// Sub gates are not associative, so chains of them are not reported.
+    UnbalancedChain { gate_id: GateId, length: usize },
+}
+
+impl Display for CircuitWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            CircuitWarning::UnusedWire { wire_id } => {
+                write!(f, "Wire with id{} is not used.", wire_id)
+            }
+            CircuitWarning::DeadWire { wire_id } => {
+                write!(f, "Wire with id{} has no path to any output wire.", wire_id)
+            }
+            CircuitWarning::HighFanOut { wire_id, fan_out } => {
+                write!(f, "Wire with id{} is read by {} gates.", wire_id, fan_out)
+            }
+            CircuitWarning::RedundantOutput { wire_id } => {
+                write!(
+                    f,
+                    "Wire with id{} is marked as output more than once.",
+                    wire_id
+                )
+            }
+            CircuitWarning::UnbalancedChain { gate_id, length } => {
+                write!(
+                    f,
+                    "Gate with id{} ends a chain of {} gates of the same type.",
+                    gate_id, length
+                )
+            }
+        }
+    }
+}
+
+impl Circuit {
+    /// Collect all the soft diagnostics of this circuit.
+    /// Warnings are grouped by kind, and ordered by wire or gate id within each kind.
+    pub fn lint(&self) -> Vec<CircuitWarning> {
+        let mut warnings = vec![];
+        let wire_count = self.get_wire_count();
+        let gates = self.get_all_gates();
+        let connections = self.wire_connections();
+        // a gate reading a wire twice counts once
+        let consumers: Vec<Vec<GateId>> = connections
+            .iter()
+            .map(WireConnection::distinct_to_ids)
+            .collect();
+
+        let mut connected = vec![false; wire_count];
+        for wire in self.get_all_inputs().iter().chain(self.get_all_outputs()) {
+            connected[wire.0] = true;
+        }
+        for constant in self.get_constants() {
+            connected[constant.get_wire_id().0] = true;
+        }
+        for gate in gates {
+            for input in gate.get_inputs() {
+                connected[input.0] = true;
+            }
+            connected[gate.get_output().0] = true;
+        }
+
+        // wires below the offset are reserved and don't belong to this circuit
+        let wire_offset = self.get_wire_offset();
+        for (wire_id, is_connected) in connected.iter().enumerate().skip(wire_offset) {
+            if !is_connected {
+                warnings.push(CircuitWarning::UnusedWire { wire_id });
+            }
+        }
+
+        let reachable = backward_reachable(self, self.get_all_outputs());
+        for wire_id in wire_offset..wire_count {
+            if connected[wire_id] && !reachable[wire_id] {
+                warnings.push(CircuitWarning::DeadWire { wire_id });
+            }
+        }
+
+        for (wire_id, gate_ids) in consumers.iter().enumerate() {
+            if gate_ids.len() > HIGH_FAN_OUT_THRESHOLD {
+                warnings.push(CircuitWarning::HighFanOut {
+                    wire_id,
+                    fan_out: gate_ids.len(),
+                });
+            }
+        }
+
+        let mut output_marks = vec![0; wire_count];
+        for out in self.get_all_outputs() {
+            output_marks[out.0] += 1;
+            if output_marks[out.0] == 2 {
+                warnings.push(CircuitWarning::RedundantOutput { wire_id: out.0 });
+            }
+        }
+
+        // chains can only be measured on an acyclic circuit.
+        if let Ok(layers) = self.gate_layers() {
+            let mut is_output = vec![false; wire_count];
+            for out in self.get_all_outputs() {
+                is_output[out.0] = true;
+            }
+
+            // a gate continues the chain of a predecessor with the same type,
+            // if the predecessor's output is read only by this gate.
+            let mut chain_length = vec![0; gates.len()];
+            for id in layers.into_iter().flatten() {
+                let gate = &gates[id];
+                let longest_predecessor = gate
+                    .get_inputs()
+                    .into_iter()
+                    .filter(|w| consumers[w.0].len() == 1 && !is_output[w.0])
+                    .filter_map(|w| connections[w.0].from_id)
+                    .filter(|p| gates[*p].gate_type() == gate.gate_type())
+                    .map(|p| chain_length[p])
+                    .max()
+                    .unwrap_or(0);
+                chain_length[id] = longest_predecessor + 1;
+            }
+
+            for (gate_id, gate) in gates.iter().enumerate() {
+                let out = gate.get_output();
+                let continued = consumers[out.0].len() == 1
+                    && !is_output[out.0]
+                    && gates[consumers[out.0][0]].gate_type() == gate.gate_type();
+                if !continued
+                    && gate.gate_type() != GateType::Sub
+                    && chain_length[gate_id] >= UNBALANCED_CHAIN_THRESHOLD
+                {
+                    warnings.push(CircuitWarning::UnbalancedChain {
+                        gate_id,
+                        length: chain_length[gate_id],
+                    });
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// List output wires which are also input wires, i.e. outputs passed through without any computation.
+    /// Such circuits are legal, but a pass-through output is often a wiring mistake.
+    /// Each wire is listed once, in output-declaration order.
+    pub fn pure_identity_outputs(&self) -> Vec<WireId> {
+        let mut is_input = vec![false; self.get_wire_count()];
+        for input in self.get_all_inputs() {
+            is_input[input.0] = true;
+        }
+
+        let mut identities = vec![];
+        for out in self.get_all_outputs() {
+            if is_input[out.0] {
+                // report each wire only once
+                is_input[out.0] = false;
+                identities.push(*out);
+            }
+        }
+        identities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CircuitWarning, HIGH_FAN_OUT_THRESHOLD};
+    use crate::circuit::*;
+
+    #[test]
+    fn clean_circuit_has_no_warnings() {
+        let mut circuit = Circuit::new();
+        let x = circuit.create_new_wire();
+        let y = circuit.create_new_wire();
+        let out = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, out);
+        circuit.mark_input(x).unwrap();
+        circuit.mark_input(y).unwrap();
+        circuit.mark_output(out).unwrap();
+
+        assert!(circuit.lint().is_empty(), "No warning should be reported");
+    }
+
+    #[test]
+    fn lint_reports_all_conditions() {
+        let mut circuit = Circuit::new();
+
+        // chain: out = (((a + b) + b) + b) + b
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        circuit.mark_input(a).unwrap();
+        circuit.mark_input(b).unwrap();
+        let mut acc = a;
+        for _ in 0..4 {
+            let next = circuit.create_new_wire();
+            circuit.add_gate(GateType::Add, acc, b, next);
+            acc = next;
+        }
+        circuit.mark_output(acc).unwrap();
+        circuit.mark_output(acc).unwrap();
+
+        // wire which is never used
+        let unused = circuit.create_new_wire();
+
+        // gate whose output is neither read nor marked as output
+        let dead = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, a, a, dead);
+
+        // many gates reading `b`
+        for _ in 0..HIGH_FAN_OUT_THRESHOLD {
+            let out = circuit.create_new_wire();
+            circuit.add_gate(GateType::Mul, b, b, out);
+            circuit.mark_output(out).unwrap();
+        }
+
+        let warnings = circuit.lint();
+        assert!(warnings.contains(&CircuitWarning::UnusedWire { wire_id: unused.0 }));
+        assert!(warnings.contains(&CircuitWarning::DeadWire { wire_id: dead.0 }));
+        assert!(warnings.contains(&CircuitWarning::HighFanOut {
+            wire_id: b.0,
+            fan_out: HIGH_FAN_OUT_THRESHOLD + 4,
+        }));
+        assert!(warnings.contains(&CircuitWarning::RedundantOutput { wire_id: acc.0 }));
+        assert!(warnings.contains(&CircuitWarning::UnbalancedChain {
+            gate_id: 3,
+            length: 4,
+        }));
+        assert_eq!(warnings.len(), 5, "Only expected warnings: {:?}", warnings);
+    }
+
+    #[test]
+    fn pass_through_output_is_reported() {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, x, y);
+        circuit.mark_output(y).unwrap();
+
+        assert_eq!(circuit.pure_identity_outputs(), vec![y]);
+    }
+
+    #[test]
+    fn warnings_are_displayable() {
+        let warning = CircuitWarning::DeadWire { wire_id: 3 };
+        assert_eq!(
+            warning.to_string(),
+            "Wire with id3 has no path to any output wire."
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Two-party garbled circuit evaluation over TCP.
+//!
+//! Both parties have to build the same circuit first. The first input wires belong to the garbler
+//! and the remaining ones to the evaluator, so each party passes only its own input values.
+//! The protocol runs as follows:
+//! 1. The garbler sends the garbled circuit, without its input keys.
+//! 2. The garbler sends the labels of its inputs.
+//! 3. The evaluator gets the labels of its inputs by oblivious transfer, see `GarbledCircuit::input_label_pairs`.
+//! 4. The evaluator evaluates the garbled circuit and sends the output labels back to the garbler.
+//!
+//! Both parties decode the output labels and return the output values.
+//! Every message is a JSON document prefixed with its length as a little-endian u64.
+//!
+//! **This is not secure.** Garbling and oblivious transfer are placeholders, see `garble` and `ot`.
+
+use crate::circuit::Circuit;
+use crate::garble::{combine_input_labels, input_choices, Evaluator, GarbledCircuit, Garbler, Prg};
+use crate::label::Label;
+use crate::ot::ObliviousTransfer;
+use crate::ring::Ring;
+use ff::PrimeField;
+use rand_core::RngCore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+fn invalid_data(message: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_message<M: Serialize>(stream: &mut impl Write, message: &M) -> io::Result<()> {
+    let bytes = serde_json::to_vec(message).map_err(invalid_data)?;
+    stream.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()
+}
+
+/// Largest message `read_message` accepts, so that the length prefix sent by the peer can't exhaust memory.
+pub const MAX_MESSAGE_LEN: u64 = 1 << 30;
+
+fn read_message<M: DeserializeOwned>(stream: &mut impl Read) -> io::Result<M> {
+    let mut length = [0; 8];
+    stream.read_exact(&mut length)?;
+    let length = u64::from_le_bytes(length);
+    if length > MAX_MESSAGE_LEN {
+        return Err(invalid_data(format!(
+            "message of {} bytes is longer than {} bytes",
+            length, MAX_MESSAGE_LEN
+        )));
+    }
+
+    // the buffer grows with the bytes actually received, instead of trusting the length up front
+    let mut bytes = vec![];
+    stream.take(length).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("message ended after {} of {} bytes", bytes.len(), length),
+        ));
+    }
+    serde_json::from_slice(&bytes).map_err(invalid_data)
+}
+
+/// Oblivious transfer over the protocol stream.
+///
+/// **This is not secure**, for the same reason as `InsecureOt`: both labels of every pair are sent.
+struct StreamOt<'a, S> {
+    stream: &'a mut S,
+}
+
+impl<'a, S: Read + Write, T: Serialize + DeserializeOwned> ObliviousTransfer<T>
+    for StreamOt<'a, S>
+{
+    fn send(&mut self, pairs: &[(Label<T>, Label<T>)]) -> io::Result<()> {
+        write_message(&mut *self.stream, &pairs)
+    }
+
+    fn receive(&mut self, choices: &[bool]) -> io::Result<Vec<Label<T>>> {
+        let pairs: Vec<(Label<T>, Label<T>)> = read_message(&mut *self.stream)?;
+        if pairs.len() != choices.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} choices for {} label pairs", choices.len(), pairs.len()),
+            ));
+        }
+
+        Ok(pairs
+            .into_iter()
+            .zip(choices)
+            .map(|((zero, one), choice)| if *choice { one } else { zero })
+            .collect())
+    }
+}
+
+/// Number of inputs of the other party, given that one party has `own` of them.
+fn other_input_count(circuit: &Circuit, own: usize) -> io::Result<usize> {
+    let total = circuit.get_all_inputs().len();
+    total.checked_sub(own).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} input values for a circuit with {} inputs", own, total),
+        )
+    })
+}
+
+/// Run the garbler side of the protocol, with `inputs` as the values of the first input wires.
+/// Returns the output values.
+pub fn run_garbler<T>(
+    circuit: &Circuit,
+    inputs: Vec<T>,
+    mut stream: TcpStream,
+) -> io::Result<Vec<T>>
+where
+    T: Ring + PrimeField + Serialize + DeserializeOwned,
+{
+    let evaluator_inputs = other_input_count(circuit, inputs.len())?;
+
+    // std seeds every RandomState with fresh random keys
+    let mut prg = Prg::new(RandomState::new().build_hasher().finish());
+    let gc = Garbler::new(prg.next_u64()).garble::<T>(circuit);
+    write_message(&mut stream, &gc)?;
+
+    let garbler_count = inputs.len();
+    let labels: Vec<Label<T>> = inputs
+        .into_iter()
+        .enumerate()
+        .map(|(position, value)| gc.encode_input(position, value))
+        .collect();
+    write_message(&mut stream, &labels)?;
+
+    let pairs = gc.input_label_pairs(garbler_count..garbler_count + evaluator_inputs, &mut prg);
+    StreamOt {
+        stream: &mut stream,
+    }
+    .send(&pairs)?;
+
+    let outputs: Vec<Label<T>> = read_message(&mut stream)?;
+    if outputs.len() != circuit.get_all_outputs().len() {
+        return Err(invalid_data("wrong number of output labels"));
+    }
+    gc.decode_outputs(outputs)
+        .ok_or_else(|| invalid_data("output label is not valid"))
+}
+
+/// Run the evaluator side of the protocol, with `inputs` as the values of the last input wires.
+/// Returns the output values.
+pub fn run_evaluator<T>(
+    circuit: &Circuit,
+    inputs: Vec<T>,
+    mut stream: TcpStream,
+) -> io::Result<Vec<T>>
+where
+    T: Ring + PrimeField + Serialize + DeserializeOwned,
+{
+    let garbler_inputs = other_input_count(circuit, inputs.len())?;
+
+    let gc: GarbledCircuit<T> = read_message(&mut stream)?;
+    if !gc.fits(circuit) {
+        return Err(invalid_data("garbled circuit doesn't match the circuit"));
+    }
+    let mut labels: Vec<Label<T>> = read_message(&mut stream)?;
+    if labels.len() != garbler_inputs {
+        return Err(invalid_data("wrong number of garbler input labels"));
+    }
+
+    let chosen = StreamOt {
+        stream: &mut stream,
+    }
+    .receive(&input_choices(&inputs))?;
+    labels.extend(combine_input_labels(chosen));
+
+    let outputs = Evaluator::new(circuit)
+        .evaluate(&gc, labels)
+        .map_err(|e| invalid_data(format!("{:?}", e)))?;
+    write_message(&mut stream, &outputs)?;
+
+    gc.decode_outputs(outputs)
+        .ok_or_else(|| invalid_data("output label is not valid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_message, run_evaluator, run_garbler, MAX_MESSAGE_LEN};
+    use crate::circuit::*;
+    use crate::test_utils::Fp;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    // out1 = x * y + z
+    // out2 = x - z
+    fn circuit() -> Circuit {
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let z = circuit.add_input();
+        let product = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, x, y, product);
+        circuit.add_output_gate(GateType::Add, product, z);
+        circuit.add_output_gate(GateType::Sub, x, z);
+        circuit
+    }
+
+    #[test]
+    fn parties_agree_on_outputs() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // x belongs to the garbler, y and z to the evaluator
+        let garbler = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            run_garbler(&circuit(), vec![Fp::from(3)], stream)
+        });
+        let stream = TcpStream::connect(address).unwrap();
+        let evaluator_outputs =
+            run_evaluator(&circuit(), vec![Fp::from(4), Fp::from(5)], stream).unwrap();
+        let garbler_outputs = garbler.join().unwrap().unwrap();
+
+        let expected = vec![Fp::from(17), Fp::from(3) - Fp::from(5)];
+        assert_eq!(evaluator_outputs, expected);
+        assert_eq!(garbler_outputs, expected);
+    }
+
+    #[test]
+    fn oversized_message_is_rejected() {
+        let prefix = (MAX_MESSAGE_LEN + 1).to_le_bytes();
+        let error = read_message::<Vec<u64>>(&mut &prefix[..]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let truncated = [&8u64.to_le_bytes()[..], b"[1]"].concat();
+        let error = read_message::<Vec<u64>>(&mut &truncated[..]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,111 @@
+//! Oblivious transfer of labels: the sender offers two labels for each item of a batch,
+//! and the receiver gets one of them by its choice bit.
+//! This is how the evaluator gets the labels of its own inputs from the garbler, see `garble`.
+//!
+//! Only `InsecureOt` is implemented for now, as a placeholder until a real protocol is in place.
+
+use crate::label::Label;
+use std::io;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+pub trait ObliviousTransfer<T> {
+    /// Offer a batch of label pairs. The first label of a pair is chosen by `false`, the second one by `true`.
+    fn send(&mut self, pairs: &[(Label<T>, Label<T>)]) -> io::Result<()>;
+
+    /// Receive a batch offered by the other party, with one choice for each pair.
+    /// Fails with `io::ErrorKind::InvalidInput` if the number of choices doesn't match the batch.
+    fn receive(&mut self, choices: &[bool]) -> io::Result<Vec<Label<T>>>;
+}
+
+/// Oblivious transfer over an in-memory channel, for tests.
+///
+/// **This is not secure.** The sender hands over both labels of every pair,
+/// so the receiver learns the labels it didn't choose.
+pub struct InsecureOt<T> {
+    sender: Sender<Vec<(Label<T>, Label<T>)>>,
+    receiver: Receiver<Vec<(Label<T>, Label<T>)>>,
+}
+
+impl<T> InsecureOt<T> {
+    /// Returns two connected ends. A batch sent by one of them is received by the other.
+    pub fn pair() -> (InsecureOt<T>, InsecureOt<T>) {
+        let (first_sender, second_receiver) = channel();
+        let (second_sender, first_receiver) = channel();
+        let first = InsecureOt {
+            sender: first_sender,
+            receiver: first_receiver,
+        };
+        let second = InsecureOt {
+            sender: second_sender,
+            receiver: second_receiver,
+        };
+        (first, second)
+    }
+}
+
+impl<T: Clone> ObliviousTransfer<T> for InsecureOt<T> {
+    fn send(&mut self, pairs: &[(Label<T>, Label<T>)]) -> io::Result<()> {
+        self.sender
+            .send(pairs.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "receiver is gone"))
+    }
+
+    fn receive(&mut self, choices: &[bool]) -> io::Result<Vec<Label<T>>> {
+        let pairs = self
+            .receiver
+            .recv()
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "sender is gone"))?;
+        if pairs.len() != choices.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} choices for {} label pairs", choices.len(), pairs.len()),
+            ));
+        }
+
+        Ok(pairs
+            .into_iter()
+            .zip(choices)
+            .map(|((zero, one), choice)| if *choice { one } else { zero })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InsecureOt, ObliviousTransfer};
+    use crate::label::{Key, Label, LabelPair};
+    use crate::test_utils::Fp;
+    use std::io;
+
+    #[test]
+    fn receiver_gets_chosen_labels() {
+        let pairs: Vec<(Label<Fp>, Label<Fp>)> = (0..8)
+            .map(|i| {
+                let pair = LabelPair::new(&Key([Fp::from(i), Fp::from(i * 3 + 1)]));
+                (pair.zero, pair.one)
+            })
+            .collect();
+        let choices = [true, false, false, true, true, true, false, true];
+
+        let (mut sender, mut receiver) = InsecureOt::pair();
+        sender.send(&pairs).unwrap();
+        let labels = receiver.receive(&choices).unwrap();
+
+        let expected: Vec<Label<Fp>> = pairs
+            .into_iter()
+            .zip(choices)
+            .map(|((zero, one), choice)| if choice { one } else { zero })
+            .collect();
+        assert_eq!(labels, expected);
+    }
+
+    #[test]
+    fn choice_count_must_match() {
+        let pair = LabelPair::new(&Key([Fp::from(1), Fp::from(2)]));
+        let (mut sender, mut receiver) = InsecureOt::pair();
+        sender.send(&[(pair.zero, pair.one)]).unwrap();
+
+        let error = receiver.receive(&[true, false]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
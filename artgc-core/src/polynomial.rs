@@ -0,0 +1,243 @@
+//! Extraction of the polynomial computed by a circuit, for comparing it against a symbolic reference.
+
+use crate::analysis::backward_reachable;
+use crate::circuit::{Circuit, WireId};
+use crate::error::CircuitResult;
+use crate::ring::Ring;
+use crate::topo::topological_sort;
+use std::collections::BTreeMap;
+use std::ops::{Add, Mul, Sub};
+
+/// Product of variables with their exponents, sorted by wire id. Variables are identified by their wire.
+/// The empty monomial is 1.
+pub type Monomial = Vec<(WireId, u32)>;
+
+/// Sparse multivariate polynomial with coefficients in `T`.
+/// Terms with coefficient zero are never stored, so equal polynomials compare equal.
+///
+/// Polynomials form a ring themselves, so a circuit can be evaluated over them like over any other ring.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial<T: Ring> {
+    terms: BTreeMap<Monomial, T>,
+}
+
+impl<T: Ring> Polynomial<T> {
+    pub fn constant(value: T) -> Self {
+        Polynomial::from_terms([(vec![], value)])
+    }
+
+    /// Polynomial made of the variable of `wire` alone.
+    pub fn variable(wire: WireId) -> Self {
+        Polynomial::from_terms([(vec![(wire, 1)], T::one())])
+    }
+
+    /// Returns the nonzero terms, ordered by monomial.
+    pub fn terms(&self) -> &BTreeMap<Monomial, T> {
+        &self.terms
+    }
+
+    /// Sum of `terms`. Monomials must be sorted by wire id.
+    fn from_terms(terms: impl IntoIterator<Item = (Monomial, T)>) -> Self {
+        let mut polynomial = Polynomial {
+            terms: BTreeMap::new(),
+        };
+        for (monomial, coefficient) in terms {
+            polynomial.add_term(monomial, coefficient);
+        }
+        polynomial
+    }
+
+    fn add_term(&mut self, monomial: Monomial, coefficient: T) {
+        let sum = match self.terms.remove(&monomial) {
+            Some(existing) => existing + coefficient,
+            None => coefficient,
+        };
+        if sum != T::zero() {
+            self.terms.insert(monomial, sum);
+        }
+    }
+}
+
+/// Product of two monomials, keeping variables sorted by wire id.
+fn multiply_monomials(a: &Monomial, b: &Monomial) -> Monomial {
+    let mut product = a.clone();
+    for (wire, exponent) in b {
+        match product.binary_search_by_key(wire, |(w, _)| *w) {
+            Ok(i) => product[i].1 += exponent,
+            Err(i) => product.insert(i, (*wire, *exponent)),
+        }
+    }
+    product
+}
+
+impl<T: Ring> Add for Polynomial<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self + &rhs
+    }
+}
+
+impl<'a, T: Ring> Add<&'a Polynomial<T>> for Polynomial<T> {
+    type Output = Self;
+
+    fn add(mut self, rhs: &'a Self) -> Self {
+        for (monomial, coefficient) in rhs.terms.iter() {
+            self.add_term(monomial.clone(), coefficient.clone());
+        }
+        self
+    }
+}
+
+impl<T: Ring> Sub for Polynomial<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self - &rhs
+    }
+}
+
+impl<'a, T: Ring> Sub<&'a Polynomial<T>> for Polynomial<T> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: &'a Self) -> Self {
+        for (monomial, coefficient) in rhs.terms.iter() {
+            self.add_term(monomial.clone(), T::zero() - coefficient);
+        }
+        self
+    }
+}
+
+impl<T: Ring> Mul for Polynomial<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self * &rhs
+    }
+}
+
+impl<'a, T: Ring> Mul<&'a Polynomial<T>> for Polynomial<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: &'a Self) -> Self {
+        Polynomial::from_terms(self.terms.iter().flat_map(|(a, x)| {
+            rhs.terms
+                .iter()
+                .map(move |(b, y)| (multiply_monomials(a, b), x.clone() * y))
+        }))
+    }
+}
+
+impl<T: Ring> Ring for Polynomial<T> {
+    fn zero() -> Self {
+        Polynomial {
+            terms: BTreeMap::new(),
+        }
+    }
+
+    fn one() -> Self {
+        Polynomial::constant(T::one())
+    }
+}
+
+impl Circuit {
+    /// Returns the polynomial over `T` computed on wire `output`, by evaluating the gates it depends on
+    /// with polynomials instead of values.
+    /// Input wires, and other wires which are not produced by any gate, are variables.
+    /// Constant wires are their value, or variables if it is not a `T`.
+    ///
+    /// The polynomial is expanded, so its number of terms can grow exponentially with the depth of the circuit,
+    /// e.g. for a product of many sums. Use it on small circuits only.
+    ///
+    /// Returns `CircuitError::UnknownWire` if `output` is not a wire of the circuit,
+    /// and `CircuitError::CyclicPath` if the gates can't be ordered.
+    pub fn to_polynomial<T: Ring>(&self, output: WireId) -> CircuitResult<Polynomial<T>> {
+        self.check_wire(output)?;
+        let order = topological_sort(self)?;
+        let needed = backward_reachable(self, &[output]);
+
+        let mut polynomials: Vec<Option<Polynomial<T>>> = vec![None; self.get_wire_count()];
+        for constant in self.get_constants() {
+            if let Some(value) = constant.get_value::<T>() {
+                polynomials[constant.get_wire_id().0] = Some(Polynomial::constant(value.clone()));
+            }
+        }
+
+        let gates = self.get_all_gates();
+        for id in order {
+            let gate = &gates[id];
+            if !needed[gate.get_output().0] {
+                continue;
+            }
+            let inputs = gate.get_inputs().into_iter().map(|wire| {
+                polynomials[wire.0]
+                    .clone()
+                    .unwrap_or_else(|| Polynomial::variable(wire))
+            });
+            polynomials[gate.get_output().0] = Some(gate.evaluate(inputs));
+        }
+
+        Ok(polynomials[output.0]
+            .take()
+            .unwrap_or_else(|| Polynomial::variable(output)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Polynomial;
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::ring::Ring;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn polynomial_of_sum_times_variable() {
+        // out = (x + y) * z
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let z = circuit.add_input();
+        let sum = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, x, y, sum);
+        let out = circuit.add_output_gate(GateType::Mul, sum, z);
+
+        let polynomial = circuit.to_polynomial::<Fp>(out).unwrap();
+        let variable = Polynomial::<Fp>::variable;
+        assert_eq!(
+            polynomial,
+            variable(x) * variable(z) + variable(y) * variable(z)
+        );
+        assert_eq!(polynomial.terms().len(), 2);
+        assert_eq!(
+            polynomial.terms().get(&vec![(x, 1), (z, 1)]),
+            Some(&Fp::one())
+        );
+    }
+
+    #[test]
+    fn polynomial_with_constants_and_cancellation() {
+        // out = (x + 2) * (x - 2) + 4 - x * x = 0
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let two = circuit.create_constant_wire(Fp::from(2));
+        let four = circuit.create_constant_wire(Fp::from(4));
+        let plus = circuit.add_output_gate(GateType::Add, x, two);
+        let minus = circuit.add_output_gate(GateType::Sub, x, two);
+        let product = circuit.add_output_gate(GateType::Mul, plus, minus);
+        let square = circuit.add_output_gate(GateType::Mul, x, x);
+        let shifted = circuit.add_output_gate(GateType::Add, product, four);
+        let out = circuit.add_output_gate(GateType::Sub, shifted, square);
+
+        assert_eq!(
+            circuit.to_polynomial::<Fp>(product),
+            Ok(Polynomial::variable(x) * Polynomial::variable(x)
+                - Polynomial::constant(Fp::from(4)))
+        );
+        assert_eq!(circuit.to_polynomial::<Fp>(out), Ok(Polynomial::zero()));
+        assert_eq!(
+            circuit.to_polynomial::<Fp>(WireId(99)),
+            Err(CircuitError::UnknownWire { wire_id: 99 })
+        );
+    }
+}
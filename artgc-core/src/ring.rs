@@ -1,18 +1,101 @@
-use std::fmt::Debug;
-use std::ops::{Add, Mul};
+//! Values carried by wires. Circuits are evaluated over any `Ring`.
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::ops::{Add, Mul, Sub};
+use ff::PrimeField;
+use rand_core::RngCore;
 
 pub trait Ring:
     'static
     + Sized
     + Eq
-    + Copy
     + Clone
     + Send
     + Sync
     + Debug
     + Add<Output = Self>
+    + Sub<Output = Self>
     + Mul<Output = Self>
     + for<'a> Add<&'a Self, Output = Self>
+    + for<'a> Sub<&'a Self, Output = Self>
     + for<'a> Mul<&'a Self, Output = Self>
 {
+    /// Additive identity.
+    fn zero() -> Self;
+
+    /// Multiplicative identity.
+    fn one() -> Self;
+}
+
+/// Ring whose elements can be sampled uniformly at random, e.g. for random testing of circuits.
+/// Every prime field is one.
+pub trait SampleRing: Ring {
+    fn random(rng: &mut impl RngCore) -> Self;
+}
+
+impl<T: Ring + PrimeField> SampleRing for T {
+    fn random(rng: &mut impl RngCore) -> Self {
+        <T as ff::Field>::random(rng)
+    }
+}
+
+/// Ring whose elements have a byte representation, e.g. for sending values to the other party.
+/// `from_bytes(&x.to_bytes())` gives back exactly `x`.
+/// Every prime field is one, represented by the bytes of its canonical `PrimeField::Repr`.
+pub trait RingBytes: Ring {
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Returns None if `bytes` doesn't represent an element, e.g. because it is truncated.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+impl<T: Ring + PrimeField> RingBytes for T {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_repr().as_ref().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut repr = T::Repr::default();
+        if bytes.len() != repr.as_ref().len() {
+            return None;
+        }
+        repr.as_mut().copy_from_slice(bytes);
+        T::from_repr(repr).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ring;
+    use super::RingBytes;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn identities() {
+        let x = Fp::from(42);
+        assert_eq!(Fp::zero() + x, x);
+        assert_eq!(Fp::one() * x, x);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        for value in [
+            Fp::zero(),
+            Fp::one(),
+            Fp::from(42),
+            Fp::zero() - Fp::from(7),
+        ] {
+            assert_eq!(Fp::from_bytes(&value.to_bytes()), Some(value));
+        }
+    }
+
+    #[test]
+    fn invalid_bytes_are_rejected() {
+        let bytes = Fp::from(42).to_bytes();
+        assert_eq!(Fp::from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Fp::from_bytes(&[]), None);
+        // larger than the modulus
+        assert_eq!(Fp::from_bytes(&vec![0xff; bytes.len()]), None);
+    }
 }
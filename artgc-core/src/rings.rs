@@ -0,0 +1,144 @@
+//! Rings to evaluate circuits over, besides the prime fields of `ff`.
+
+use crate::ring::{Ring, RingBytes, SampleRing};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Sub};
+use rand_core::RngCore;
+
+/// Element of GF(2), so that boolean circuits can be built with the existing gates:
+/// - Add gates compute XOR.
+/// - Sub gates compute XOR too, since every element is its own additive inverse.
+/// - Mul gates compute AND.
+/// - Neg gates compute the identity.
+///
+/// There is no gate for NOT, but `x XOR 1` computes it with a constant wire holding `Bit(true)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Bit(pub bool);
+
+impl From<bool> for Bit {
+    fn from(value: bool) -> Self {
+        Bit(value)
+    }
+}
+
+impl Add for Bit {
+    type Output = Bit;
+
+    fn add(self, rhs: Bit) -> Bit {
+        Bit(self.0 != rhs.0)
+    }
+}
+
+impl Sub for Bit {
+    type Output = Bit;
+
+    fn sub(self, rhs: Bit) -> Bit {
+        Bit(self.0 != rhs.0)
+    }
+}
+
+impl Mul for Bit {
+    type Output = Bit;
+
+    fn mul(self, rhs: Bit) -> Bit {
+        Bit(self.0 && rhs.0)
+    }
+}
+
+impl<'a> Add<&'a Bit> for Bit {
+    type Output = Bit;
+
+    fn add(self, rhs: &'a Bit) -> Bit {
+        self + *rhs
+    }
+}
+
+impl<'a> Sub<&'a Bit> for Bit {
+    type Output = Bit;
+
+    fn sub(self, rhs: &'a Bit) -> Bit {
+        self - *rhs
+    }
+}
+
+impl<'a> Mul<&'a Bit> for Bit {
+    type Output = Bit;
+
+    fn mul(self, rhs: &'a Bit) -> Bit {
+        self * *rhs
+    }
+}
+
+impl Ring for Bit {
+    fn zero() -> Self {
+        Bit(false)
+    }
+
+    fn one() -> Self {
+        Bit(true)
+    }
+}
+
+impl SampleRing for Bit {
+    fn random(rng: &mut impl RngCore) -> Self {
+        Bit(rng.next_u32() & 1 == 1)
+    }
+}
+
+/// A single byte, 0 or 1.
+impl RingBytes for Bit {
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![self.0 as u8]
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(Bit(false)),
+            [1] => Some(Bit(true)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bit;
+    use crate::circuit::*;
+    use crate::eval_local::eval_local;
+
+    #[test]
+    fn half_adder() {
+        let mut circuit = Circuit::new();
+
+        // sum = a XOR b
+        // carry = a AND b
+        let a = circuit.add_input();
+        let b = circuit.add_input();
+        circuit.add_output_gate(GateType::Add, a, b);
+        circuit.add_output_gate(GateType::Mul, a, b);
+
+        for (a, b, sum, carry) in [
+            (false, false, false, false),
+            (false, true, true, false),
+            (true, false, true, false),
+            (true, true, false, true),
+        ] {
+            assert_eq!(
+                eval_local(&circuit, vec![Bit(a), Bit(b)]),
+                Ok(vec![Bit(sum), Bit(carry)])
+            );
+        }
+    }
+
+    #[test]
+    fn not_with_constant_one() {
+        let mut circuit = Circuit::new();
+        let a = circuit.add_input();
+        let one = circuit.create_constant_wire(Bit(true));
+        circuit.add_output_gate(GateType::Add, a, one);
+
+        assert_eq!(eval_local(&circuit, vec![Bit(false)]), Ok(vec![Bit(true)]));
+        assert_eq!(eval_local(&circuit, vec![Bit(true)]), Ok(vec![Bit(false)]));
+    }
+}
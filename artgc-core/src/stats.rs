@@ -0,0 +1,189 @@
+//! One-call summary of the size of a circuit, for logging and for choosing how to garble it.
+
+use crate::circuit::{Circuit, GateType, WireId};
+use std::fmt::Display;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub num_inputs: usize,
+    pub num_outputs: usize,
+    /// Linear gates: Add, AddMany, Sub, Neg and ScalarMul gates. They are cheap to garble.
+    pub num_add_gates: usize,
+    pub num_mul_gates: usize,
+    /// Wires of this circuit, not counting the ids below its wire offset.
+    pub num_wires: usize,
+    /// See `Circuit::depth`. None if the gates can't be ordered.
+    pub depth: Option<usize>,
+}
+
+impl Circuit {
+    pub fn stats(&self) -> CircuitStats {
+        let mut num_add_gates = 0;
+        let mut num_mul_gates = 0;
+        for gate in self.gate_stream() {
+            match gate.gate_type() {
+                GateType::Add | GateType::Sub | GateType::Neg | GateType::ScalarMul => {
+                    num_add_gates += 1
+                }
+                GateType::Mul => num_mul_gates += 1,
+            }
+        }
+
+        CircuitStats {
+            num_inputs: self.get_all_inputs().len(),
+            num_outputs: self.get_all_outputs().len(),
+            num_add_gates,
+            num_mul_gates,
+            num_wires: self.get_wire_count() - self.get_wire_offset(),
+            depth: self.depth().ok(),
+        }
+    }
+
+    /// Number of Mul gates, the only gates whose output isn't a linear function of their inputs.
+    /// Add, AddMany, Sub, Neg and ScalarMul gates are linear.
+    /// Gates are counted by type, so a Mul gate reading a constant wire is counted too.
+    pub fn num_nonlinear_gates(&self) -> usize {
+        self.gate_stream()
+            .filter(|gate| gate.gate_type() == GateType::Mul)
+            .count()
+    }
+
+    /// Whether every gate of the circuit is linear, see `num_nonlinear_gates`.
+    /// Every output of a linear circuit is then a linear combination of the inputs and constants.
+    pub fn is_linear(&self) -> bool {
+        self.num_nonlinear_gates() == 0
+    }
+
+    /// Returns how many gate inputs read `wire`. Garbling copies the label of the wire to each of them.
+    /// A gate reading the wire as both of its inputs counts twice.
+    pub fn fanout(&self, wire: WireId) -> usize {
+        self.wire_connections()
+            .get(wire.0)
+            .map_or(0, |connection| connection.to_ids.len())
+    }
+
+    /// Returns the wire with the highest fan-out and its fan-out, see `fanout`.
+    /// Ties go to the lowest wire id. A circuit without wires gives its wire offset and 0.
+    pub fn max_fanout(&self) -> (WireId, usize) {
+        let mut max = (WireId(self.get_wire_offset()), 0);
+        for (wire_id, connection) in self
+            .wire_connections()
+            .iter()
+            .enumerate()
+            .skip(self.get_wire_offset())
+        {
+            if connection.to_ids.len() > max.1 {
+                max = (WireId(wire_id), connection.to_ids.len());
+            }
+        }
+        max
+    }
+}
+
+impl Display for CircuitStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} inputs, {} outputs, {} add gates, {} mul gates, {} wires, depth ",
+            self.num_inputs,
+            self.num_outputs,
+            self.num_add_gates,
+            self.num_mul_gates,
+            self.num_wires
+        )?;
+        match self.depth {
+            Some(depth) => write!(f, "{}", depth),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CircuitStats;
+    use crate::circuit::*;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn stats_of_multiple_outputs() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        let stats = circuit.stats();
+        assert_eq!(
+            stats,
+            CircuitStats {
+                num_inputs: 3,
+                num_outputs: 2,
+                num_add_gates: 1,
+                num_mul_gates: 1,
+                num_wires: 5,
+                depth: Some(2),
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "3 inputs, 2 outputs, 1 add gates, 1 mul gates, 5 wires, depth 2"
+        );
+    }
+
+    #[test]
+    fn linear_and_nonlinear_circuits() {
+        // out = -(x + y - z) * 3
+        let mut circuit = Circuit::new();
+        let x = circuit.add_input();
+        let y = circuit.add_input();
+        let z = circuit.add_input();
+        let sum = circuit.add_many(&[x, y]);
+        let diff = circuit.create_new_wire();
+        circuit.add_gate(GateType::Sub, sum, z, diff);
+        let neg = circuit.add_neg(diff);
+        let out = circuit.add_scalar_mul(neg, Fp::from(3));
+        circuit.mark_output(out).unwrap();
+        assert!(circuit.is_linear());
+        assert_eq!(circuit.num_nonlinear_gates(), 0);
+
+        circuit.add_output_gate(GateType::Mul, out, x);
+        circuit.add_output_gate(GateType::Mul, x, y);
+        assert!(!circuit.is_linear());
+        assert_eq!(circuit.num_nonlinear_gates(), 2);
+    }
+
+    #[test]
+    fn fanout_of_multiple_outputs() {
+        let mut circuit = Circuit::new();
+
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        // out3 = in3 - in2
+        let in1 = circuit.add_input();
+        let in2 = circuit.add_input();
+        let in3 = circuit.add_input();
+        let out1 = circuit.add_output_gate(GateType::Add, in1, in2);
+        let out2 = circuit.add_output_gate(GateType::Mul, in3, out1);
+        circuit.add_output_gate(GateType::Sub, in3, in2);
+
+        assert_eq!(circuit.fanout(out1), 1);
+        assert_eq!(circuit.fanout(in1), 1);
+        assert_eq!(circuit.fanout(in3), 2);
+        assert_eq!(circuit.fanout(out2), 0);
+        assert_eq!(circuit.max_fanout(), (in2, 2));
+    }
+}
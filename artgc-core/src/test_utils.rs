@@ -0,0 +1,160 @@
+//! Helpers shared by unit tests.
+
+use crate::ring::Ring;
+use ff::PrimeField;
+use rand_core::{impls, Error, RngCore};
+use std::cell::Cell;
+use std::ops::{Add, Mul, Sub};
+
+// Use finite field as a Ring
+// ff implements similar
+#[derive(PrimeField)]
+#[PrimeFieldModulus = "52435875175126190479447740508185965837690552500527637822603658699938581184513"]
+#[PrimeFieldGenerator = "7"]
+#[PrimeFieldReprEndianness = "little"]
+pub struct Fp([u64; 4]);
+impl Ring for Fp {
+    fn zero() -> Self {
+        <Fp as ff::Field>::ZERO
+    }
+
+    fn one() -> Self {
+        <Fp as ff::Field>::ONE
+    }
+}
+
+/// Serialized as the bytes of its canonical representation, see `RingBytes`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Fp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&crate::ring::RingBytes::to_bytes(self), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Fp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let bytes: Vec<u8> = serde::Deserialize::deserialize(deserializer)?;
+        crate::ring::RingBytes::from_bytes(&bytes)
+            .ok_or_else(|| D::Error::custom("bytes are not a field element"))
+    }
+}
+
+/// Small deterministic xorshift generator so that tests are reproducible.
+pub struct TestRng(pub u64);
+
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+thread_local! {
+    static CLONES: Cell<usize> = const { Cell::new(0) };
+    static OPERATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Ring over u64 which counts how many times its values are cloned
+/// and how many gate operations are computed on the current thread.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Counted(pub u64);
+
+impl Counted {
+    pub fn reset() {
+        CLONES.with(|c| c.set(0));
+        OPERATIONS.with(|c| c.set(0));
+    }
+
+    pub fn clones() -> usize {
+        CLONES.with(|c| c.get())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn operations() -> usize {
+        OPERATIONS.with(|c| c.get())
+    }
+
+    fn count_operation() {
+        OPERATIONS.with(|c| c.set(c.get() + 1));
+    }
+}
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        CLONES.with(|c| c.set(c.get() + 1));
+        Counted(self.0)
+    }
+}
+
+impl Add for Counted {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        self + &rhs
+    }
+}
+
+impl<'a> Add<&'a Counted> for Counted {
+    type Output = Self;
+    fn add(self, rhs: &'a Self) -> Self {
+        Counted::count_operation();
+        Counted(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for Counted {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self - &rhs
+    }
+}
+
+impl<'a> Sub<&'a Counted> for Counted {
+    type Output = Self;
+    fn sub(self, rhs: &'a Self) -> Self {
+        Counted::count_operation();
+        Counted(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Mul for Counted {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        self * &rhs
+    }
+}
+
+impl<'a> Mul<&'a Counted> for Counted {
+    type Output = Self;
+    fn mul(self, rhs: &'a Self) -> Self {
+        Counted::count_operation();
+        Counted(self.0.wrapping_mul(rhs.0))
+    }
+}
+
+impl Ring for Counted {
+    fn zero() -> Self {
+        Counted(0)
+    }
+
+    fn one() -> Self {
+        Counted(1)
+    }
+}
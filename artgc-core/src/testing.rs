@@ -0,0 +1,72 @@
+//! Generators of circuits for property tests, built with the `testing` feature.
+
+use crate::circuit::{Circuit, GateType, WireId};
+use rand_core::RngCore;
+
+/// Returns a circuit with `num_inputs` inputs and `num_gates` random Add and Mul gates.
+/// Each gate reads two wires picked uniformly among the inputs and the outputs of the gates before it,
+/// so the circuit is acyclic. Outputs of gates which no other gate reads are marked as outputs, in gate order,
+/// so the circuit is valid. Inputs may be left unused.
+///
+/// Panics if `num_inputs` or `num_gates` is 0.
+pub fn random_circuit(num_inputs: usize, num_gates: usize, rng: &mut impl RngCore) -> Circuit {
+    assert!(num_inputs > 0, "gates need an input to read");
+    assert!(num_gates > 0, "a circuit needs a gate to have an output");
+
+    let mut circuit = Circuit::new();
+    let mut wires: Vec<WireId> = (0..num_inputs).map(|_| circuit.add_input()).collect();
+    let mut read = vec![false; num_inputs + num_gates];
+
+    for _ in 0..num_gates {
+        let gate_type = if rng.next_u32() & 1 == 0 {
+            GateType::Add
+        } else {
+            GateType::Mul
+        };
+        let x = random_wire(&wires, rng);
+        let y = random_wire(&wires, rng);
+        read[x.0] = true;
+        read[y.0] = true;
+
+        let out = circuit.create_new_wire();
+        circuit.add_gate(gate_type, x, y, out);
+        wires.push(out);
+    }
+
+    for out in wires.into_iter().skip(num_inputs) {
+        if !read[out.0] {
+            // every wire is new, so it can't be a duplicate output
+            circuit.mark_output(out).unwrap();
+        }
+    }
+    circuit
+}
+
+fn random_wire(wires: &[WireId], rng: &mut impl RngCore) -> WireId {
+    wires[rng.next_u64() as usize % wires.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::random_circuit;
+    use crate::detect_cycle::detect_cycle;
+    use crate::eval_local::eval_local;
+    use crate::test_utils::{Fp, TestRng};
+
+    #[test]
+    fn random_circuits_are_valid_and_evaluable() {
+        let mut rng = TestRng(7);
+        for i in 0..100 {
+            let num_inputs = 1 + i % 5;
+            let num_gates = 1 + i % 23;
+            let circuit = random_circuit(num_inputs, num_gates, &mut rng);
+
+            assert_eq!(circuit.get_gate_count(), num_gates);
+            assert_eq!(circuit.is_valid(), Ok(()));
+            assert_eq!(detect_cycle(&circuit), None);
+            let inputs = (0..num_inputs as u64).map(Fp::from).collect();
+            let outputs = eval_local(&circuit, inputs).unwrap();
+            assert_eq!(outputs.len(), circuit.get_all_outputs().len());
+        }
+    }
+}
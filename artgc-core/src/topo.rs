@@ -0,0 +1,192 @@
+//! Ordering of gates by their dependencies.
+
+use crate::circuit::{Circuit, Gate};
+use crate::error::{CircuitError, CircuitResult};
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Returns ids of all the gates, ordered so that every gate comes after the gates producing its input wires.
+/// Gates which don't depend on each other keep their gate id order.
+///
+/// Returns `CircuitError::CyclicPath` if no such order exists.
+/// The error holds a gate which couldn't be ordered and its input wire which was never produced.
+pub fn topological_sort(circuit: &Circuit) -> CircuitResult<Vec<usize>> {
+    let wire_connections = circuit.wire_connections();
+    let gates = circuit.get_all_gates();
+
+    // number of input wires of each gate whose producing gate isn't ordered yet
+    let mut pending = vec![0; gates.len()];
+    for (id, gate) in gates.iter().enumerate() {
+        for wire in gate.get_inputs() {
+            if wire_connections[wire.0].from_id.is_some() {
+                pending[id] += 1;
+            }
+        }
+    }
+
+    let mut ready: VecDeque<usize> = (0..gates.len()).filter(|id| pending[*id] == 0).collect();
+    let mut order = Vec::with_capacity(gates.len());
+
+    while let Some(id) = ready.pop_front() {
+        order.push(id);
+
+        let out = gates[id].get_output();
+        if wire_connections[out.0].from_id != Some(id) {
+            // another gate drives this wire too, and its consumers wait for that one
+            continue;
+        }
+        for next in wire_connections[out.0].to_ids.iter() {
+            pending[*next] -= 1;
+            if pending[*next] == 0 {
+                ready.push_back(*next);
+            }
+        }
+    }
+
+    if order.len() < gates.len() {
+        // a gate is left out only if one of its producing gates is left out too
+        let gate_id = (0..gates.len()).find(|id| pending[*id] != 0).unwrap();
+        let wire = gates[gate_id]
+            .get_inputs()
+            .into_iter()
+            .find(|wire| {
+                wire_connections[wire.0]
+                    .from_id
+                    .is_some_and(|from| pending[from] != 0)
+            })
+            .unwrap();
+        return Err(CircuitError::CyclicPath {
+            gate_id,
+            wire_id: wire.0,
+        });
+    }
+
+    Ok(order)
+}
+
+impl Circuit {
+    /// Iterate over all the gates in the order of `topological_sort`, so that every gate comes after
+    /// the gates producing its input wires, and gates which don't depend on each other keep their id order.
+    /// The order only depends on the structure of the circuit, so garbling in this order is reproducible
+    /// however the gates were added.
+    ///
+    /// A cyclic circuit has no such order and yields no gates. Callers are expected to validate the circuit first.
+    pub fn iter_topo(&self) -> impl Iterator<Item = &Gate> {
+        let gates = self.get_all_gates();
+        topological_sort(self)
+            .unwrap_or_default()
+            .into_iter()
+            .map(move |id| &gates[id])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::topological_sort;
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+
+    #[test]
+    fn gates_come_after_their_producers() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+
+        // gate1
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        let gate1 = circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        // gate2
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        let gate2 = circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out1).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        assert_eq!(topological_sort(&circuit), Ok(vec![gate1, gate2]));
+    }
+
+    #[test]
+    fn gates_added_out_of_order() {
+        let mut circuit = Circuit::new();
+
+        // same circuit as above, but gate2 is added first
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let in3 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        let gate2 = circuit.add_gate(GateType::Mul, in3, out1, out2);
+        let gate1 = circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        assert_eq!(topological_sort(&circuit), Ok(vec![gate1, gate2]));
+    }
+
+    #[test]
+    fn cycle_has_no_order() {
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, input, b, a);
+        circuit.add_gate(GateType::Mul, input, a, b);
+        circuit.mark_output(b).unwrap();
+
+        assert!(matches!(
+            topological_sort(&circuit),
+            Err(CircuitError::CyclicPath { .. })
+        ));
+    }
+
+    #[test]
+    fn iter_topo_ignores_build_order() {
+        // out = (x + y) * z, with the Mul gate added first or last
+        fn build(mul_first: bool) -> Circuit {
+            let mut circuit = Circuit::new();
+            let x = circuit.add_input();
+            let y = circuit.add_input();
+            let z = circuit.add_input();
+            let sum = circuit.create_new_wire();
+            if mul_first {
+                circuit.add_output_gate(GateType::Mul, sum, z);
+                circuit.add_gate(GateType::Add, x, y, sum);
+            } else {
+                circuit.add_gate(GateType::Add, x, y, sum);
+                circuit.add_output_gate(GateType::Mul, sum, z);
+            }
+            circuit
+        }
+
+        let types = |circuit: &Circuit| -> Vec<GateType> {
+            circuit.iter_topo().map(|gate| gate.gate_type()).collect()
+        };
+        assert_eq!(types(&build(false)), vec![GateType::Add, GateType::Mul]);
+        assert_eq!(types(&build(true)), types(&build(false)));
+    }
+
+    #[test]
+    fn iter_topo_of_cycle_is_empty() {
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input();
+        let a = circuit.create_new_wire();
+        let b = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, input, b, a);
+        circuit.add_gate(GateType::Mul, input, a, b);
+
+        assert_eq!(circuit.iter_topo().count(), 0);
+    }
+}
@@ -0,0 +1,133 @@
+//! Record of a circuit evaluation which can be replayed later.
+//! A trace holds the input values, the output value of every gate and the output values,
+//! each paired with the id of the wire it was carried on.
+//! Verifying a trace re-evaluates the circuit from the recorded inputs and compares every recorded value.
+
+use crate::circuit::{Circuit, WireId};
+use crate::error::{CircuitError, CircuitResult};
+use crate::eval_local::{check_empty_wires, check_input_length, evaluate_wires, EvalLocalError};
+use crate::ring::Ring;
+use std::cmp::max;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvalTrace<T: Ring> {
+    /// Values of input wires in input-declaration order.
+    pub inputs: Vec<(WireId, T)>,
+    /// Values of output wires of gates in gate id order.
+    pub gate_outputs: Vec<(WireId, T)>,
+    /// Values of output wires of the circuit in output-declaration order.
+    pub outputs: Vec<(WireId, T)>,
+}
+
+/// Evaluate a circuit like `eval_local` and record the evaluation as a trace.
+pub fn eval_local_record<T: Ring>(
+    circuit: &Circuit,
+    input_values: Vec<T>,
+) -> Result<(Vec<T>, EvalTrace<T>), EvalLocalError> {
+    check_input_length(circuit, &input_values)?;
+    let (values, empty) = evaluate_wires(circuit, input_values, true);
+    check_empty_wires(empty)?;
+
+    let record = |wire: &WireId| (*wire, values[wire.0].clone().unwrap());
+    let trace = EvalTrace {
+        inputs: circuit.get_all_inputs().iter().map(record).collect(),
+        gate_outputs: circuit
+            .get_all_gates()
+            .iter()
+            .map(|gate| record(&gate.get_output()))
+            .collect(),
+        outputs: circuit.get_all_outputs().iter().map(record).collect(),
+    };
+    let outputs = trace.outputs.iter().map(|(_, v)| v.clone()).collect();
+
+    Ok((outputs, trace))
+}
+
+/// Check that the recorded wires are exactly the expected wires, in the same order.
+fn check_wires<T: Ring>(expected: &[WireId], recorded: &[(WireId, T)]) -> CircuitResult<()> {
+    for i in 0..max(expected.len(), recorded.len()) {
+        match (expected.get(i), recorded.get(i)) {
+            (Some(e), Some((r, _))) if e == r => {}
+            (Some(wire), _) | (None, Some((wire, _))) => {
+                return Err(CircuitError::TraceMismatch { wire_id: wire.0 });
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    Ok(())
+}
+
+impl<T: Ring> EvalTrace<T> {
+    /// Re-evaluate the circuit from the recorded inputs and confirm the trace matches.
+    /// Returns `CircuitError::TraceMismatch` with the first wire whose recorded value differs,
+    /// or which is recorded on the wrong position.
+    pub fn verify(&self, circuit: &Circuit) -> CircuitResult<()> {
+        let gate_outputs: Vec<WireId> = circuit
+            .get_all_gates()
+            .iter()
+            .map(|gate| gate.get_output())
+            .collect();
+        check_wires(circuit.get_all_inputs(), &self.inputs)?;
+        check_wires(&gate_outputs, &self.gate_outputs)?;
+        check_wires(circuit.get_all_outputs(), &self.outputs)?;
+
+        let input_values = self.inputs.iter().map(|(_, v)| v.clone()).collect();
+        let (values, _) = evaluate_wires(circuit, input_values, true);
+
+        for (wire, value) in self.gate_outputs.iter().chain(self.outputs.iter()) {
+            if values[wire.0].as_ref() != Some(value) {
+                return Err(CircuitError::TraceMismatch { wire_id: wire.0 });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::eval_local_record;
+    use crate::circuit::*;
+    use crate::error::CircuitError;
+    use crate::test_utils::Fp;
+
+    #[test]
+    fn record_and_verify_trace() {
+        let mut circuit = Circuit::new();
+
+        // Circuit
+        // out1 = in1 + in2
+        // out2 = (in1 + in2) * in3
+        let in1 = circuit.create_new_wire();
+        let in2 = circuit.create_new_wire();
+        let out1 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Add, in1, in2, out1);
+
+        let in3 = circuit.create_new_wire();
+        let out2 = circuit.create_new_wire();
+        circuit.add_gate(GateType::Mul, in3, out1, out2);
+
+        circuit.mark_input(in1).unwrap();
+        circuit.mark_input(in2).unwrap();
+        circuit.mark_input(in3).unwrap();
+        circuit.mark_output(out2).unwrap();
+
+        let inputs: Vec<Fp> = vec![1.into(), 2.into(), 3.into()];
+        let (outputs, mut trace) = eval_local_record(&circuit, inputs).unwrap();
+        assert_eq!(outputs, vec![9.into()]);
+        assert_eq!(trace.gate_outputs, vec![(out1, 3.into()), (out2, 9.into())]);
+        assert_eq!(
+            trace.verify(&circuit),
+            Ok(()),
+            "Recorded trace should verify"
+        );
+
+        // tamper the intermediate value
+        trace.gate_outputs[0].1 = 4.into();
+        assert_eq!(
+            trace.verify(&circuit),
+            Err(CircuitError::TraceMismatch { wire_id: out1.0 }),
+            "Tampered trace should not verify"
+        );
+    }
+}
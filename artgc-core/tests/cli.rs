@@ -0,0 +1,76 @@
+//! Runs the `artgc` binary on the circuits in `fixtures`.
+#![cfg(feature = "cli")]
+
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Run `artgc` on the fixture `circuit` with the JSON text `inputs`, which is written to a temporary file.
+fn artgc(circuit: &str, inputs: &str, extra: &[&str]) -> Output {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let inputs_path = std::env::temp_dir().join(format!(
+        "artgc-cli-{}-{}.json",
+        std::process::id(),
+        RUNS.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&inputs_path, inputs).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_artgc"))
+        .arg("--circuit")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/").to_string() + circuit)
+        .arg("--inputs")
+        .arg(&inputs_path)
+        .args(extra)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&inputs_path).unwrap();
+    output
+}
+
+#[test]
+fn prints_outputs() {
+    // out1 = x + y
+    // out2 = (x + y) * z
+    let output = artgc("sum_product.json", "[2, 3, 4]", &[]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "5\n20\n");
+}
+
+#[test]
+fn evaluates_over_the_chosen_field() {
+    let inputs = "[2147483647, 1, 2]";
+    let output = artgc("sum_product.json", inputs, &[]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "2147483648\n4294967296\n"
+    );
+
+    let output = artgc("sum_product.json", inputs, &["--modulus", "m31"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n2\n");
+}
+
+#[test]
+fn rejects_cyclic_circuit() {
+    let output = artgc("cyclic.json", "[1]", &[]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("cyclic path"), "{}", stderr);
+}
+
+#[test]
+fn rejects_wrong_number_of_inputs() {
+    let output = artgc("sum_product.json", "[1, 2]", &[]);
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("3 input"), "{}", stderr);
+}
+
+#[test]
+fn reports_unparsable_files() {
+    let output = artgc("sum_product.json", "{\"x\": 1}", &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+
+    let output = artgc("missing.json", "[1]", &[]);
+    assert_eq!(output.status.code(), Some(1));
+}